@@ -1,42 +1,68 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+use anchor_lang::solana_program::hash::hash;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer, Mint};
-use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::associated_token::{get_associated_token_address, AssociatedToken};
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_interface::{self, Token2022};
+use anchor_spl::token_interface::spl_token_2022::extension::{
+    transfer_hook::TransferHookAccount, BaseStateWithExtensions, StateWithExtensions,
+};
+use anchor_spl::token_2022_extensions::spl_pod::optional_keys::OptionalNonZeroPubkey;
+use anchor_spl::token_2022_extensions::spl_token_metadata_interface::state::TokenMetadata;
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, state::ExtraAccountMetaList};
+use spl_transfer_hook_interface::instruction::ExecuteInstruction;
 
 declare_id!("ASA8xRVPFBQLo3dLJQH2NedBKJWsVXGu46radY6oRX6i");
 
+/// Mint key used in PDA seeds and quotes to denote native SOL (lamports)
+pub const NATIVE_SOL_MINT: Pubkey = Pubkey::new_from_array([0; 32]);
+pub const SOL_DECIMALS: u8 = 9;
+/// Maximum length of the payer memo attached to a payment
+pub const MAX_MEMO_LEN: usize = 128;
+/// Fiat amounts passed to `execute_transfer_fiat` are in millionths of the currency unit
+pub const FIAT_DECIMALS: u32 = 6;
+/// Largest price exponent magnitude accepted from a feed (Pyth feeds use -12..=0 in practice)
+pub const MAX_PRICE_EXPONENT: u32 = 12;
+pub const MAX_REFERRERS: usize = 10;
+pub const MAX_FILTER_MINTS: usize = 16;
+/// Token metadata symbol of alias NFTs (the name is the alias itself)
+pub const ALIAS_NFT_SYMBOL: &str = "UNIK";
+/// Config.paused_flags bits - each halts one class of instructions
+pub const PAUSE_REGISTRATIONS: u8 = 1 << 0;
+pub const PAUSE_ROUTE_EDITS: u8 = 1 << 1;
+pub const PAUSE_SOL_TRANSFERS: u8 = 1 << 2;
+pub const PAUSE_TOKEN_TRANSFERS: u8 = 1 << 3;
+pub const PAUSE_MARKETPLACE: u8 = 1 << 4;
+pub const PAUSE_ALL: u8 =
+    PAUSE_REGISTRATIONS | PAUSE_ROUTE_EDITS | PAUSE_SOL_TRANSFERS | PAUSE_TOKEN_TRANSFERS | PAUSE_MARKETPLACE;
+/// Upper bound for Config.royalty_bps (10%)
+pub const MAX_ROYALTY_BPS: u16 = 1000;
+/// A new auction bid must beat the highest one by this much (5%)
+pub const MIN_BID_INCREMENT_BPS: u64 = 500;
+
 #[program]
 pub mod unik_anchor {
     use super::*;
 
     pub fn register_alias(ctx: Context<RegisterAlias>, alias: String, metadata_uri: String) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REGISTRATIONS)?;
+        // Names up for auction go to the highest bidder through settle_auction
+        require!(ctx.accounts.auction.data_is_empty(), UnikError::AliasInAuction);
+
         // A. Alias Normalization and Validation
-        require!(alias.len() >= 3 && alias.len() <= 32, UnikError::InvalidAliasLength);
-        require!(alias.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'), UnikError::InvalidAliasCharacters);
+        require_valid_alias(&alias)?;
         
         // E. Metadata Validation
         require!(metadata_uri.len() <= 200, UnikError::MetadataTooLong);
 
-        let alias_account = &mut ctx.accounts.alias_account;
-        alias_account.owner = ctx.accounts.user.key();
-        alias_account.alias = alias.clone();
-        alias_account.metadata_uri = metadata_uri;
-        alias_account.version = 1;  // Initial version
-        alias_account.is_active = true;  // Active by default
-        alias_account.registered_at = Clock::get()?.unix_timestamp;
-        alias_account.bump = ctx.bumps.alias_account;
-        
-        msg!("Alias registered: {} (version 1)", alias_account.alias);
-        emit!(AliasEvent {
-            event_type: "REGISTER".to_string(),
-            alias: alias_account.alias.clone(),
-            owner: alias_account.owner,
-            timestamp: alias_account.registered_at,
-        });
-        Ok(())
+        let owner = ctx.accounts.user.key();
+        init_alias_account(&mut ctx.accounts.alias_account, owner, alias, metadata_uri, ctx.bumps.alias_account)
     }
 
     pub fn init_route_config(ctx: Context<InitRouteConfig>, alias: String) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_ROUTE_EDITS)?;
         let route_account = &mut ctx.accounts.route_account;
         let alias_account = &ctx.accounts.alias_account;
 
@@ -45,12 +71,16 @@ pub mod unik_anchor {
         route_account.alias_ref = alias_account.key();
         route_account.splits = Vec::new(); // Initialize empty splits
         route_account.bump = ctx.bumps.route_account;
+        route_account.schema_version = RouteAccount::SCHEMA_VERSION;
+        route_account.consented_mask = 0;
+        route_account.sol_delivery = Vec::new();
 
         msg!("Route config initialized for alias: {}", alias);
         Ok(())
     }
 
     pub fn set_route_config(ctx: Context<SetRouteConfig>, alias: String, splits: Vec<Split>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_ROUTE_EDITS)?;
         let route_account = &mut ctx.accounts.route_account;
         let alias_account = &ctx.accounts.alias_account;
         
@@ -85,132 +115,1061 @@ pub mod unik_anchor {
         route_account.alias_ref = alias_account.key();
         
         let splits_len = splits.len();
+        let previous_splits_hash = route_account.splits_hash()?;
         route_account.splits = splits;
+        // Consent and delivery preferences are per split index; they don't carry over to a new route
+        route_account.consented_mask = 0;
+        route_account.sol_delivery = Vec::new();
         
         msg!("Route config set for alias: {} with {} splits", alias, splits_len);
-        emit!(RouteEvent {
+        emit!(RouteSetEvent {
+            alias: alias.clone(),
+            splits: route_account.splits.clone(),
+            previous_splits_hash,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Record that the signing recipient consents to being listed in the alias route.
+    /// Consent is tracked per split index and cleared whenever the route is reconfigured.
+    pub fn accept_split(ctx: Context<AcceptSplit>, alias: String) -> Result<()> {
+        let route_account = &mut ctx.accounts.route_account;
+        let recipient = ctx.accounts.recipient.key();
+
+        let split_index = route_account.splits.iter()
+            .position(|s| s.recipient == recipient)
+            .ok_or(UnikError::NotASplitRecipient)?;
+
+        route_account.consented_mask |= 1 << split_index;
+
+        msg!("Recipient {} accepted split {} for alias: {}", recipient, split_index, alias);
+        emit!(SplitConsentEvent {
             alias: alias.clone(),
-            splits_count: splits_len as u8,
+            recipient,
+            split_index: split_index as u8,
+            consented_mask: route_account.consented_mask,
             timestamp: Clock::get()?.unix_timestamp,
         });
         Ok(())
     }
 
-    pub fn execute_transfer<'info>(ctx: Context<'_, '_, '_, 'info, ExecuteTransfer<'info>>, _alias: String, amount: u64) -> Result<()> {
-        require!(amount >= 10000, UnikError::AmountTooSmall);
+    /// Choose per split how SOL reaches the recipient (owner only): `Wrapped` splits of a native SOL
+    /// payment receive wSOL in their ATA, `Native` splits of a wSOL token payment receive unwrapped lamports.
+    /// One preference per split, in split order; cleared whenever the route is reconfigured.
+    pub fn set_split_delivery(ctx: Context<SetSplitDelivery>, alias: String, sol_delivery: Vec<SolDelivery>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_ROUTE_EDITS)?;
+        let route_account = &mut ctx.accounts.route_account;
+        require!(sol_delivery.len() == route_account.splits.len(), UnikError::InvalidDeliveryPreferences);
+
+        route_account.sol_delivery = sol_delivery;
+
+        msg!("Split delivery preferences set for alias: {}", alias);
+        Ok(())
+    }
+
+    /// Pay `amount` lamports to an alias, split according to its route.
+    /// An optional `reference` account (Solana Pay style) and `memo` are echoed in the PaymentEvent
+    /// so the payment can be matched to an order deterministically.
+    /// A non-zero `referral_bps` carves that share out for `referrer` before the splits,
+    /// within the alias's referral policy.
+    /// With `expected_route_hash` (see `RouteAccount::splits_hash`) the payment fails if the splits changed.
+    /// remaining_accounts: one writable destination per split, in split order (see `QuoteLeg::account`).
+    pub fn execute_transfer<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteTransfer<'info>>,
+        alias: String,
+        amount: u64,
+        memo: Option<String>,
+        referral_bps: u16,
+        expected_route_hash: Option<[u8; 32]>,
+    ) -> Result<()> {
+        process_sol_payment(ctx.accounts, ctx.remaining_accounts, alias, amount, memo, referral_bps, expected_route_hash)
+    }
+
+    /// Pay a fiat-denominated amount in SOL. `fiat_amount` (in millionths, see FIAT_DECIMALS) is
+    /// converted to lamports with the owner-configured price feed for `currency`, which must be
+    /// fresh and tight enough; `max_lamports` protects the payer against price moves.
+    #[allow(clippy::too_many_arguments)]
+    pub fn execute_transfer_fiat<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteTransferFiat<'info>>,
+        alias: String,
+        currency: [u8; 3],
+        fiat_amount: u64,
+        max_lamports: u64,
+        memo: Option<String>,
+        referral_bps: u16,
+        expected_route_hash: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let fiat_feed = &ctx.accounts.fiat_feed;
+        let price = read_pyth_price(&ctx.accounts.price_feed.try_borrow_data()?)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        require_usable_price(&price, fiat_feed, now)?;
+
+        let lamports = fiat_to_lamports(fiat_amount, &price)?;
+        require!(lamports <= max_lamports, UnikError::PriceSlippageExceeded);
+
+        msg!("Converted {} {} (1e-6) to {} lamports", fiat_amount, String::from_utf8_lossy(&currency), lamports);
+        emit!(FiatConversionEvent {
+            alias: alias.clone(),
+            currency,
+            fiat_amount,
+            lamports,
+            price: price.price,
+            expo: price.expo,
+            timestamp: now,
+        });
+
+        process_sol_payment(&ctx.accounts.transfer, ctx.remaining_accounts, alias, lamports, memo, referral_bps, expected_route_hash)
+    }
+
+    /// SPL token counterpart of `execute_transfer`; recipients (and the referrer) receive funds in their ATAs.
+    /// remaining_accounts: same canonical layout as `execute_transfer`.
+    pub fn execute_token_transfer<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteTokenTransfer<'info>>,
+        alias: String,
+        amount: u64,
+        memo: Option<String>,
+        referral_bps: u16,
+        expected_route_hash: Option<[u8; 32]>,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TOKEN_TRANSFERS)?;
+        require_mint_accepted(&ctx.accounts.mint_filter, &ctx.accounts.mint.key())?;
+        require_within_limits(&ctx.accounts.payment_limits, amount, ctx.accounts.mint.decimals)?;
+        require_memo_len(&memo)?;
+        require_route_hash(&ctx.accounts.route_account, expected_route_hash)?;
 
-        // CRIT-01: Verify the alias is active before accepting payments
-        // Backwards compatibility: legacy aliases have registered_at = 0, so treat them as active
-        require!(ctx.accounts.alias_account.is_active || ctx.accounts.alias_account.registered_at == 0, UnikError::AliasInactive);
+        require_accepting_payments(&ctx.accounts.alias_account)?;
 
         let route = &ctx.accounts.route_account;
         let splits = &route.splits;
         let remaining_accounts = ctx.remaining_accounts;
+        let mint_key = ctx.accounts.mint.key();
 
-        msg!("Executing transfer of {} lamports for {} splits", amount, splits.len());
+        msg!("Executing TOKEN transfer of {} units for {} splits", amount, splits.len());
 
-        let user_info = ctx.accounts.user.to_account_info();
-        let system_program_info = ctx.accounts.system_program.to_account_info();
+        let user_token_info = ctx.accounts.user_token_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let authority_info = ctx.accounts.user.to_account_info();
 
-        let mut total_sent: u64 = 0;
-        let splits_len = splits.len();
+        let referral = referral_share(
+            ctx.accounts.referral_policy.as_deref(),
+            ctx.accounts.referrer.as_ref().map(|referrer| referrer.key()),
+            &ctx.accounts.user.key(),
+            amount,
+            referral_bps,
+        )?;
+        let split_total = match &referral {
+            Some((referrer, referral_amount)) => {
+                let referrer_ata = ctx.accounts.referrer_token_account.as_ref().ok_or(UnikError::MissingReferrer)?;
+                require!(
+                    referrer_ata.key() == get_associated_token_address(referrer, &mint_key),
+                    UnikError::InvalidRecipientAta
+                );
+                if *referral_amount > 0 {
+                    let cpi_accounts = Transfer {
+                        from: user_token_info.clone(),
+                        to: referrer_ata.to_account_info(),
+                        authority: authority_info.clone(),
+                    };
+                    token::transfer(CpiContext::new(token_program_info.clone(), cpi_accounts), *referral_amount)?;
+                }
+                amount - referral_amount
+            }
+            None => amount,
+        };
 
-        for (i, split) in splits.iter().enumerate() {
-            let recipient_acc = remaining_accounts.iter()
-                .find(|acc| acc.key() == split.recipient)
-                .ok_or(UnikError::MissingRecipient)?;
+        // wSOL payments to recipients preferring native SOL are unwrapped on the way
+        let unwrap_legs: Vec<bool> = (0..splits.len())
+            .map(|i| mint_key == native_mint::ID && route.sol_delivery_for(i) == SolDelivery::Native)
+            .collect();
+
+        if unwrap_legs.contains(&true) {
+            distribute_wsol_unwrapping(ctx.accounts, splits, &unwrap_legs, split_total, remaining_accounts)?;
+        } else {
+            distribute_tokens(
+                splits,
+                split_total,
+                &mint_key,
+                &user_token_info,
+                &authority_info,
+                &token_program_info,
+                remaining_accounts,
+                &[],
+            )?;
+        }
+        
+        record_payment_stats(&ctx.accounts.payment_stats, &ctx.accounts.user.key(), amount)?;
 
-            // CRIT-01 explicit security lock
-            require!(recipient_acc.key() == split.recipient, UnikError::InvalidRecipientAccount);
+        if let Some(receipt) = &ctx.accounts.receipt {
+            let reference = ctx.accounts.reference.as_ref().ok_or(UnikError::MissingReference)?;
+            create_receipt(
+                receipt,
+                &authority_info,
+                &ctx.accounts.system_program.to_account_info(),
+                Receipt {
+                    alias_ref: ctx.accounts.alias_account.key(),
+                    payer: ctx.accounts.user.key(),
+                    mint: mint_key,
+                    // Refundable amount: the referral share is not held by the route recipients
+                    amount: split_total,
+                    splits: splits.clone(),
+                    refunded_amount: 0,
+                    is_refunded: false,
+                    reference: reference.key(),
+                    created_at: Clock::get()?.unix_timestamp,
+                    bump: 0,
+                    schema_version: Receipt::SCHEMA_VERSION,
+                },
+            )?;
+        }
 
-            let mut split_amount = (amount as u128)
-                .checked_mul(split.percentage as u128)
-                .ok_or(UnikError::Overflow)?
-                .checked_div(10000)
-                .ok_or(UnikError::Overflow)? as u64;
+        if let Some((referrer, referral_amount)) = referral {
+            emit!(ReferralPaidEvent {
+                alias: alias.clone(),
+                payer: ctx.accounts.user.key(),
+                referrer,
+                mint: mint_key,
+                amount: referral_amount,
+                referral_bps,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        }
 
-            // HIGH: Dust fix - add remainder to the last recipient
-            if i == splits_len.saturating_sub(1) {
-                split_amount = amount.saturating_sub(total_sent);
-            }
+        msg!("Token transfer completed successfully");
+        emit!(PaymentEvent {
+            alias,
+            payer: ctx.accounts.user.key(),
+            mint: mint_key,
+            amount,
+            reference: ctx.accounts.reference.as_ref().map(|r| r.key()),
+            memo,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Pay several aliases in one instruction with the same mint (e.g. a payroll run).
+    /// Each entry may pin its route hash and pay a referrer like `execute_token_transfer`;
+    /// wSOL entries whose route has recipients preferring native SOL are rejected (pay those singly).
+    /// remaining_accounts, flat and per entry in order:
+    /// [alias_account, route_account, payment_limits, payment_stats (mut), mint_filter,
+    ///  (referral_policy, referrer, referrer ATA (mut) - only if referral_bps > 0), recipient ATAs in split order...]
+    pub fn batch_execute_token_transfer<'info>(ctx: Context<'_, '_, '_, 'info, BatchExecuteTokenTransfer<'info>>, entries: Vec<BatchEntry>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TOKEN_TRANSFERS)?;
+        require!(!entries.is_empty(), UnikError::EmptyBatch);
+
+        let mint_key = ctx.accounts.mint.key();
+        let decimals = ctx.accounts.mint.decimals;
+        let payer = ctx.accounts.user.key();
+        let user_token_info = ctx.accounts.user_token_account.to_account_info();
+        let authority_info = ctx.accounts.user.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let reference = ctx.accounts.reference.as_ref().map(|r| r.key());
+        let timestamp = Clock::get()?.unix_timestamp;
+
+        let mut remaining_accounts = ctx.remaining_accounts;
+        for entry in entries {
+            require!(remaining_accounts.len() >= 5, UnikError::MissingRecipient);
+            let alias_seed = entry.alias.as_bytes();
+
+            let alias_account: AliasAccount = load_remaining_pda(&remaining_accounts[0], &[b"alias", alias_seed])?;
+            let route_account: RouteAccount = load_remaining_pda(&remaining_accounts[1], &[b"route", alias_seed])?;
+            // CRIT-02: Validate route belongs to this alias
+            require!(route_account.alias_ref == remaining_accounts[0].key(), UnikError::InvalidPDA);
+            require_accepting_payments(&alias_account)?;
+
+            let payment_limits = &remaining_accounts[2];
+            let payment_stats = &remaining_accounts[3];
+            require_pda(payment_limits, &[b"limits", alias_seed, mint_key.as_ref()])?;
+            require_pda(payment_stats, &[b"stats", alias_seed, mint_key.as_ref()])?;
+            require_within_limits(payment_limits, entry.amount, decimals)?;
+
+            let mint_filter = &remaining_accounts[4];
+            require_pda(mint_filter, &[b"mints", alias_seed])?;
+            require_mint_accepted(mint_filter, &mint_key)?;
+            require_route_hash(&route_account, entry.expected_route_hash)?;
+            require!(
+                mint_key != native_mint::ID
+                    || (0..route_account.splits.len()).all(|i| route_account.sol_delivery_for(i) != SolDelivery::Native),
+                UnikError::SolDeliveryUnsupported
+            );
+            remaining_accounts = &remaining_accounts[5..];
 
-            if split_amount > 0 {
+            let mut split_total = entry.amount;
+            if entry.referral_bps > 0 {
+                require!(remaining_accounts.len() >= 3, UnikError::MissingReferrer);
+                require_pda(&remaining_accounts[0], &[b"referral", alias_seed])?;
+                let policy = load_optional::<ReferralPolicy>(&remaining_accounts[0])?;
+                let (referrer, referral_amount) = referral_share(
+                    policy.as_ref(),
+                    Some(remaining_accounts[1].key()),
+                    &payer,
+                    entry.amount,
+                    entry.referral_bps,
+                )?
+                .ok_or(UnikError::ReferralsNotEnabled)?;
+                let referrer_ata = &remaining_accounts[2];
                 require!(
-                    recipient_acc.owner == &system_program::ID || recipient_acc.lamports() > 0,
-                    UnikError::InvalidRecipientAccount
+                    referrer_ata.key() == get_associated_token_address(&referrer, &mint_key),
+                    UnikError::InvalidRecipientAta
                 );
+                if referral_amount > 0 {
+                    let cpi_accounts = Transfer {
+                        from: user_token_info.clone(),
+                        to: referrer_ata.clone(),
+                        authority: authority_info.clone(),
+                    };
+                    token::transfer(CpiContext::new(token_program_info.clone(), cpi_accounts), referral_amount)?;
+                }
+                split_total -= referral_amount;
+                emit!(ReferralPaidEvent {
+                    alias: entry.alias.clone(),
+                    payer,
+                    referrer,
+                    mint: mint_key,
+                    amount: referral_amount,
+                    referral_bps: entry.referral_bps,
+                    timestamp,
+                });
+                remaining_accounts = &remaining_accounts[3..];
+            }
+
+            let splits_len = route_account.splits.len();
+            require!(remaining_accounts.len() >= splits_len, UnikError::MissingRecipient);
+            distribute_tokens(
+                &route_account.splits,
+                split_total,
+                &mint_key,
+                &user_token_info,
+                &authority_info,
+                &token_program_info,
+                &remaining_accounts[..splits_len],
+                &[],
+            )?;
+            record_payment_stats(payment_stats, &payer, entry.amount)?;
+
+            emit!(PaymentEvent {
+                alias: entry.alias,
+                payer,
+                mint: mint_key,
+                amount: entry.amount,
+                reference,
+                memo: None,
+                timestamp,
+            });
+            remaining_accounts = &remaining_accounts[splits_len..];
+        }
+
+        msg!("Batch token transfer completed successfully");
+        Ok(())
+    }
+
+    /// Return `amount` of a receipted payment to its payer. Every recipient with a non-zero share
+    /// must sign; each returns its pro-rata share computed from the receipt's splits snapshot.
+    /// remaining_accounts (per split, in order) - SOL: [recipient]; tokens: [recipient, recipient ATA].
+    pub fn refund_payment<'info>(ctx: Context<'_, '_, '_, 'info, RefundPayment<'info>>, amount: u64) -> Result<()> {
+        let receipt = &mut ctx.accounts.receipt;
+        require!(amount > 0, UnikError::InvalidRefundAmount);
+        let refunded_amount = receipt.refunded_amount.checked_add(amount).ok_or(UnikError::Overflow)?;
+        require!(refunded_amount <= receipt.amount, UnikError::InvalidRefundAmount);
+
+        let is_sol = receipt.mint == NATIVE_SOL_MINT;
+        let accounts_per_split = if is_sol { 1 } else { 2 };
+        let remaining_accounts = ctx.remaining_accounts;
+        require!(remaining_accounts.len() >= receipt.splits.len() * accounts_per_split, UnikError::MissingRecipient);
 
+        let amounts = split_amounts(&receipt.splits, amount)?;
+        let payer_info = ctx.accounts.payer.to_account_info();
+
+        for (i, (split, share)) in receipt.splits.iter().zip(amounts).enumerate() {
+            if share == 0 {
+                continue;
+            }
+            let recipient = &remaining_accounts[i * accounts_per_split];
+            require!(recipient.key() == split.recipient, UnikError::InvalidRecipientAccount);
+            require!(recipient.is_signer, UnikError::RecipientMustSign);
+
+            if is_sol {
                 let cpi_context = CpiContext::new(
-                    system_program_info.clone(),
+                    ctx.accounts.system_program.to_account_info(),
                     system_program::Transfer {
-                        from: user_info.clone(),
-                        to: recipient_acc.clone(),
+                        from: recipient.clone(),
+                        to: payer_info.clone(),
                     }
                 );
-                system_program::transfer(cpi_context, split_amount)?;
-                total_sent = total_sent.checked_add(split_amount).ok_or(UnikError::Overflow)?;
+                system_program::transfer(cpi_context, share)?;
+            } else {
+                let recipient_ata = &remaining_accounts[i * accounts_per_split + 1];
+                require!(
+                    recipient_ata.key() == get_associated_token_address(&split.recipient, &receipt.mint),
+                    UnikError::InvalidRecipientAta
+                );
+                let payer_token_account = ctx.accounts.payer_token_account.as_ref().ok_or(UnikError::InvalidUserTokenAccount)?;
+                let token_program = ctx.accounts.token_program.as_ref().ok_or(UnikError::MintMismatch)?;
+
+                let cpi_accounts = Transfer {
+                    from: recipient_ata.clone(),
+                    to: payer_token_account.to_account_info(),
+                    authority: recipient.clone(),
+                };
+                token::transfer(CpiContext::new(token_program.to_account_info(), cpi_accounts), share)?;
             }
         }
+
+        receipt.refunded_amount = refunded_amount;
+        receipt.is_refunded = refunded_amount == receipt.amount;
+
+        msg!("Refunded {} of {} to {}", amount, receipt.amount, receipt.payer);
+        emit!(RefundEvent {
+            receipt: receipt.key(),
+            payer: receipt.payer,
+            mint: receipt.mint,
+            amount,
+            refunded_amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
 
-    pub fn execute_token_transfer<'info>(ctx: Context<'_, '_, '_, 'info, ExecuteTokenTransfer<'info>>, _alias: String, amount: u64) -> Result<()> {
-        require!(amount >= 10000, UnikError::AmountTooSmall);
+    /// Close a receipt and reclaim its rent (payer only). The payment can no longer be refunded on-chain.
+    pub fn close_receipt(_ctx: Context<CloseReceipt>) -> Result<()> {
+        msg!("Receipt closed");
+        Ok(())
+    }
 
-        // CRIT-01: Verify the alias is active before accepting payments
-        require!(ctx.accounts.alias_account.is_active || ctx.accounts.alias_account.registered_at == 0, UnikError::AliasInactive);
+    /// Pay `amount` lamports to an alias through escrow: funds are held in the Escrow PDA until
+    /// released into the route (by the payer, or by anyone after `timeout` seconds),
+    /// refunded by the alias owner, or resolved either way by the optional `arbiter`.
+    pub fn create_escrow(ctx: Context<CreateEscrow>, alias: String, escrow_id: u64, amount: u64, timeout: i64, arbiter: Option<Pubkey>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_SOL_TRANSFERS)?;
+        require_within_limits(&ctx.accounts.payment_limits, amount, SOL_DECIMALS)?;
+        require_accepting_payments(&ctx.accounts.alias_account)?;
+        require!(timeout > 0, UnikError::InvalidEscrowTimeout);
+        add_obligation(&mut ctx.accounts.alias_account)?;
 
-        let route = &ctx.accounts.route_account;
-        let splits = &route.splits;
-        let remaining_accounts = ctx.remaining_accounts;
-        let mint_key = ctx.accounts.mint.key();
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.user.to_account_info(),
+                to: ctx.accounts.escrow.to_account_info(),
+            }
+        );
+        system_program::transfer(cpi_context, amount)?;
 
-        msg!("Executing TOKEN transfer of {} units for {} splits", amount, splits.len());
+        let now = Clock::get()?.unix_timestamp;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.alias_ref = ctx.accounts.alias_account.key();
+        escrow.payer = ctx.accounts.user.key();
+        escrow.mint = NATIVE_SOL_MINT;
+        escrow.amount = amount;
+        escrow.escrow_id = escrow_id;
+        escrow.arbiter = arbiter;
+        escrow.release_after = now.checked_add(timeout).ok_or(UnikError::Overflow)?;
+        escrow.created_at = now;
+        escrow.bump = ctx.bumps.escrow;
+        escrow.schema_version = Escrow::SCHEMA_VERSION;
 
-        require!(remaining_accounts.len() >= splits.len(), UnikError::MissingRecipient);
+        msg!("Escrowed {} lamports for alias: {}", amount, alias);
+        emit!(EscrowCreatedEvent {
+            escrow: escrow.key(),
+            alias,
+            payer: escrow.payer,
+            mint: escrow.mint,
+            amount,
+            arbiter,
+            release_after: escrow.release_after,
+            timestamp: now,
+        });
+        Ok(())
+    }
 
-        let user_token_info = ctx.accounts.user_token_account.to_account_info();
-        let token_program_info = ctx.accounts.token_program.to_account_info();
-        let authority_info = ctx.accounts.user.to_account_info();
+    /// SPL token counterpart of `create_escrow`; funds are held in a vault owned by the Escrow PDA.
+    pub fn create_token_escrow(ctx: Context<CreateTokenEscrow>, alias: String, escrow_id: u64, amount: u64, timeout: i64, arbiter: Option<Pubkey>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TOKEN_TRANSFERS)?;
+        require_within_limits(&ctx.accounts.payment_limits, amount, ctx.accounts.mint.decimals)?;
+        require_accepting_payments(&ctx.accounts.alias_account)?;
+        require_mint_accepted(&ctx.accounts.mint_filter, &ctx.accounts.mint.key())?;
+        require!(timeout > 0, UnikError::InvalidEscrowTimeout);
+        add_obligation(&mut ctx.accounts.alias_account)?;
 
-        let mut total_sent: u64 = 0;
-        let splits_len = splits.len();
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.user_token_account.to_account_info(),
+            to: ctx.accounts.vault.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        token::transfer(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts), amount)?;
 
-        for (i, split) in splits.iter().enumerate() {
-            let expected_ata = get_associated_token_address(&split.recipient, &mint_key);
-            let recipient_ata = &remaining_accounts[i];
-            
-            require!(
-                recipient_ata.key() == expected_ata,
-                UnikError::InvalidRecipientAta
-            );
+        let now = Clock::get()?.unix_timestamp;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.alias_ref = ctx.accounts.alias_account.key();
+        escrow.payer = ctx.accounts.user.key();
+        escrow.mint = ctx.accounts.mint.key();
+        escrow.amount = amount;
+        escrow.escrow_id = escrow_id;
+        escrow.arbiter = arbiter;
+        escrow.release_after = now.checked_add(timeout).ok_or(UnikError::Overflow)?;
+        escrow.created_at = now;
+        escrow.bump = ctx.bumps.escrow;
+        escrow.schema_version = Escrow::SCHEMA_VERSION;
 
-            let mut split_amount = (amount as u128)
-                .checked_mul(split.percentage as u128)
-                .ok_or(UnikError::Overflow)?
-                .checked_div(10000)
-                .ok_or(UnikError::Overflow)? as u64;
+        msg!("Escrowed {} tokens for alias: {}", amount, alias);
+        emit!(EscrowCreatedEvent {
+            escrow: escrow.key(),
+            alias,
+            payer: escrow.payer,
+            mint: escrow.mint,
+            amount,
+            arbiter,
+            release_after: escrow.release_after,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Release escrowed funds into the alias route. The payer may release at any time;
+    /// anyone may release once the timeout has passed. Fails once the alias it was made to is gone.
+    /// remaining_accounts: recipient wallets (SOL) or ATAs (tokens), in split order.
+    pub fn release_escrow<'info>(ctx: Context<'_, '_, '_, 'info, SettleEscrow<'info>>, alias: String) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        require!(
+            ctx.accounts.authority.key() == escrow.payer || Clock::get()?.unix_timestamp >= escrow.release_after,
+            UnikError::EscrowLocked
+        );
+        settle_escrow(ctx, &alias, true)
+    }
+
+    /// Return escrowed funds to the payer: by the alias owner, or by the payer once the alias
+    /// it was made to has been deleted (or deleted and registered again).
+    pub fn refund_escrow<'info>(ctx: Context<'_, '_, '_, 'info, SettleEscrow<'info>>, alias: String) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        match escrow_alias(&ctx.accounts.alias_account, &ctx.accounts.escrow)? {
+            Some(alias_account) => require!(authority == alias_account.owner, UnikError::Unauthorized),
+            None => require!(authority == ctx.accounts.escrow.payer, UnikError::Unauthorized),
+        }
+        settle_escrow(ctx, &alias, false)
+    }
+
+    /// Settle a disputed escrow either way (named arbiter only)
+    pub fn resolve_escrow<'info>(ctx: Context<'_, '_, '_, 'info, SettleEscrow<'info>>, alias: String, release: bool) -> Result<()> {
+        require!(Some(ctx.accounts.authority.key()) == ctx.accounts.escrow.arbiter, UnikError::Unauthorized);
+        settle_escrow(ctx, &alias, release)
+    }
+
+    /// Subscribe to pay `amount` tokens to an alias every `period` seconds, first charge due now.
+    /// Approves the program's subscription delegate for `allowance` more tokens on the payer's
+    /// token account; `collect_subscription` pulls one period at a time within that allowance.
+    /// Each subscription tracks its own share of the approval, so several can share a token account.
+    pub fn create_subscription(ctx: Context<CreateSubscription>, alias: String, amount: u64, period: i64, allowance: u64) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TOKEN_TRANSFERS)?;
+        require_within_limits(&ctx.accounts.payment_limits, amount, ctx.accounts.mint.decimals)?;
+        require_accepting_payments(&ctx.accounts.alias_account)?;
+        require_mint_accepted(&ctx.accounts.mint_filter, &ctx.accounts.mint.key())?;
+        require!(period > 0, UnikError::InvalidSubscriptionPeriod);
+        require!(allowance >= amount, UnikError::InsufficientAllowance);
+        add_obligation(&mut ctx.accounts.alias_account)?;
+
+        // SPL token accounts have a single delegate: top up our existing approval rather than replace it
+        let user_token_account = &ctx.accounts.user_token_account;
+        let delegate_key = ctx.accounts.subscription_delegate.key();
+        let existing_allowance = if user_token_account.delegate == Some(delegate_key).into() {
+            user_token_account.delegated_amount
+        } else {
+            0
+        };
+        let cpi_accounts = token::Approve {
+            to: user_token_account.to_account_info(),
+            delegate: ctx.accounts.subscription_delegate.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        token::approve(
+            CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts),
+            existing_allowance.checked_add(allowance).ok_or(UnikError::Overflow)?,
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.payer = ctx.accounts.user.key();
+        subscription.alias_ref = ctx.accounts.alias_account.key();
+        subscription.mint = ctx.accounts.mint.key();
+        subscription.payer_token_account = user_token_account.key();
+        subscription.amount = amount;
+        subscription.period = period;
+        subscription.next_due = now;
+        subscription.allowance = allowance;
+        subscription.created_at = now;
+        subscription.bump = ctx.bumps.subscription;
+        subscription.schema_version = Subscription::SCHEMA_VERSION;
+
+        msg!("Subscription created: {} every {}s to alias: {}", amount, period, alias);
+        emit!(SubscriptionEvent {
+            subscription: subscription.key(),
+            alias,
+            payer: subscription.payer,
+            mint: subscription.mint,
+            amount,
+            period,
+            is_active: true,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Permissionless crank: charge one due period of a subscription through the alias route.
+    /// remaining_accounts: recipient ATAs, in split order.
+    pub fn collect_subscription<'info>(ctx: Context<'_, '_, '_, 'info, CollectSubscription<'info>>, alias: String) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TOKEN_TRANSFERS)?;
+        require_accepting_payments(&ctx.accounts.alias_account)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let subscription = &mut ctx.accounts.subscription;
+        require!(now >= subscription.next_due, UnikError::SubscriptionNotDue);
+        subscription.allowance = subscription.allowance
+            .checked_sub(subscription.amount)
+            .ok_or(UnikError::InsufficientAllowance)?;
+
+        distribute_tokens(
+            &ctx.accounts.route_account.splits,
+            subscription.amount,
+            &subscription.mint,
+            &ctx.accounts.payer_token_account.to_account_info(),
+            &ctx.accounts.subscription_delegate.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.remaining_accounts,
+            &[&[b"subscription_delegate", &[ctx.bumps.subscription_delegate]]],
+        )?;
+
+        record_payment_stats(&ctx.accounts.payment_stats, &subscription.payer, subscription.amount)?;
+
+        subscription.next_due = subscription.next_due.checked_add(subscription.period).ok_or(UnikError::Overflow)?;
 
-            // HIGH: Dust fix - add remainder to the last recipient
-            if i == splits_len.saturating_sub(1) {
-                split_amount = amount.saturating_sub(total_sent);
+        msg!("Subscription charged {} for alias: {}, next due {}", subscription.amount, alias, subscription.next_due);
+        emit!(SubscriptionChargedEvent {
+            subscription: subscription.key(),
+            alias,
+            payer: subscription.payer,
+            mint: subscription.mint,
+            amount: subscription.amount,
+            next_due: subscription.next_due,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Cancel a subscription: by the payer, or by the alias owner to end it (e.g. before deleting the alias).
+    /// Refunds rent to the payer. When the payer cancels, the subscription's unused allowance is taken
+    /// back off the delegate approval, revoking it once nothing is left; the token account may be
+    /// omitted if it was closed. An approval left behind can't be drawn by any other subscription.
+    pub fn cancel_subscription(ctx: Context<CancelSubscription>, alias: String) -> Result<()> {
+        let subscription = &ctx.accounts.subscription;
+        let authority = ctx.accounts.authority.key();
+        let alias_owner = load_alias(&ctx.accounts.alias_account)?.map(|alias_account| alias_account.owner);
+        require!(authority == subscription.payer || Some(authority) == alias_owner, UnikError::Unauthorized);
+
+        let payer_token_account = ctx.accounts.payer_token_account.as_ref().filter(|_| authority == subscription.payer);
+        if let Some(payer_token_account) = payer_token_account {
+            let delegate_key = ctx.accounts.subscription_delegate.key();
+            if payer_token_account.delegate == Some(delegate_key).into() {
+                let token_program = ctx.accounts.token_program.as_ref().ok_or(UnikError::MissingTokenAccount)?.to_account_info();
+                let remaining = payer_token_account.delegated_amount.saturating_sub(subscription.allowance);
+                if remaining == 0 {
+                    let cpi_accounts = token::Revoke {
+                        source: payer_token_account.to_account_info(),
+                        authority: ctx.accounts.authority.to_account_info(),
+                    };
+                    token::revoke(CpiContext::new(token_program, cpi_accounts))?;
+                } else {
+                    let cpi_accounts = token::Approve {
+                        to: payer_token_account.to_account_info(),
+                        delegate: ctx.accounts.subscription_delegate.to_account_info(),
+                        authority: ctx.accounts.authority.to_account_info(),
+                    };
+                    token::approve(CpiContext::new(token_program, cpi_accounts), remaining)?;
+                }
             }
+        }
+        release_obligation(&ctx.accounts.alias_account)?;
 
-            if split_amount > 0 {
-                msg!("Sending {} tokens to recipient {} (ATA: {})", split_amount, split.recipient, recipient_ata.key());
-                
-                let cpi_accounts = Transfer {
-                    from: user_token_info.clone(),
-                    to: recipient_ata.clone(),
-                    authority: authority_info.clone(),
-                };
-                let cpi_ctx = CpiContext::new(token_program_info.clone(), cpi_accounts);
-                token::transfer(cpi_ctx, split_amount)?;
-                total_sent = total_sent.checked_add(split_amount).ok_or(UnikError::Overflow)?;
+        msg!("Subscription cancelled for alias: {}", alias);
+        emit!(SubscriptionEvent {
+            subscription: subscription.key(),
+            alias,
+            payer: subscription.payer,
+            mint: subscription.mint,
+            amount: subscription.amount,
+            period: subscription.period,
+            is_active: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Deposit `amount` tokens that vest linearly to an alias between `start_ts` and `end_ts`
+    pub fn create_stream(ctx: Context<CreateStream>, alias: String, stream_id: u64, amount: u64, start_ts: i64, end_ts: i64) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TOKEN_TRANSFERS)?;
+        require_within_limits(&ctx.accounts.payment_limits, amount, ctx.accounts.mint.decimals)?;
+        require_accepting_payments(&ctx.accounts.alias_account)?;
+        require_mint_accepted(&ctx.accounts.mint_filter, &ctx.accounts.mint.key())?;
+        require!(end_ts > start_ts, UnikError::InvalidStreamSchedule);
+        add_obligation(&mut ctx.accounts.alias_account)?;
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.user_token_account.to_account_info(),
+            to: ctx.accounts.vault.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        token::transfer(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts), amount)?;
+
+        let stream = &mut ctx.accounts.stream;
+        stream.sender = ctx.accounts.user.key();
+        stream.alias_ref = ctx.accounts.alias_account.key();
+        stream.mint = ctx.accounts.mint.key();
+        stream.stream_id = stream_id;
+        stream.total_amount = amount;
+        stream.withdrawn = 0;
+        stream.start_ts = start_ts;
+        stream.end_ts = end_ts;
+        stream.bump = ctx.bumps.stream;
+        stream.schema_version = Stream::SCHEMA_VERSION;
+
+        msg!("Stream of {} tokens created for alias: {}", amount, alias);
+        emit!(StreamCreatedEvent {
+            stream: stream.key(),
+            alias,
+            sender: stream.sender,
+            mint: stream.mint,
+            total_amount: amount,
+            start_ts,
+            end_ts,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Permissionless: release the vested, not yet withdrawn part of a stream through the alias route.
+    /// remaining_accounts: recipient ATAs, in split order.
+    pub fn withdraw_stream<'info>(ctx: Context<'_, '_, '_, 'info, WithdrawStream<'info>>, alias: String) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TOKEN_TRANSFERS)?;
+        require!(ctx.accounts.alias_account.freeze_reason == 0, UnikError::AliasFrozen);
+        // An empty route would pay nothing yet count the release as withdrawn, stranding it in the vault
+        require!(!ctx.accounts.route_account.splits.is_empty(), UnikError::RouteNotConfigured);
+        let now = Clock::get()?.unix_timestamp;
+        let stream = &ctx.accounts.stream;
+        let releasable = stream.vested_amount(now)?.saturating_sub(stream.withdrawn);
+        require!(releasable > 0, UnikError::NothingToWithdraw);
+
+        let stream_id = stream.stream_id.to_le_bytes();
+        let signer_seeds: &[&[u8]] = &[b"stream", alias.as_bytes(), stream.sender.as_ref(), &stream_id, &[stream.bump]];
+        distribute_tokens(
+            &ctx.accounts.route_account.splits,
+            releasable,
+            &stream.mint,
+            &ctx.accounts.vault.to_account_info(),
+            &stream.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.remaining_accounts,
+            &[signer_seeds],
+        )?;
+        record_payment_stats(&ctx.accounts.payment_stats, &stream.sender, releasable)?;
+
+        let stream = &mut ctx.accounts.stream;
+        stream.withdrawn = stream.withdrawn.checked_add(releasable).ok_or(UnikError::Overflow)?;
+
+        msg!("Stream released {} tokens to alias: {}", releasable, alias);
+        emit!(StreamWithdrawnEvent {
+            stream: stream.key(),
+            alias,
+            amount: releasable,
+            withdrawn: stream.withdrawn,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Cancel a stream: by the sender, or by the alias owner to end it (e.g. before deleting the alias).
+    /// The vested part is released through the alias route and the unvested part returned to the sender,
+    /// then the stream is closed. If the alias or its route has been deleted, the vested part is
+    /// returned to the sender as well.
+    /// remaining_accounts: recipient ATAs, in split order (none when the route is gone).
+    pub fn cancel_stream<'info>(ctx: Context<'_, '_, '_, 'info, CancelStream<'info>>, alias: String) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let stream = &ctx.accounts.stream;
+        let vested = stream.vested_amount(now)?;
+        let mut releasable = vested.saturating_sub(stream.withdrawn);
+        let mut unvested = stream.total_amount.saturating_sub(vested);
+
+        let alias_account = load_alias(&ctx.accounts.alias_account)?;
+        let authority = ctx.accounts.authority.key();
+        require!(
+            authority == stream.sender || Some(authority) == alias_account.as_ref().map(|alias_account| alias_account.owner),
+            UnikError::Unauthorized
+        );
+        let route = match alias_account {
+            Some(alias_account) => load_optional::<RouteAccount>(&ctx.accounts.route_account)?
+                .filter(|route| !route.splits.is_empty())
+                .map(|route| (alias_account, route)),
+            None => None,
+        };
+        match &route {
+            Some((alias_account, _)) if releasable > 0 => {
+                require_not_paused(&ctx.accounts.config, PAUSE_TOKEN_TRANSFERS)?;
+                require_accepting_payments(alias_account)?;
+            }
+            Some(_) => {}
+            None => {
+                unvested = unvested.checked_add(releasable).ok_or(UnikError::Overflow)?;
+                releasable = 0;
             }
         }
-        
-        msg!("Token transfer completed successfully");
+
+        let stream_id = stream.stream_id.to_le_bytes();
+        let signer_seeds: &[&[u8]] = &[b"stream", alias.as_bytes(), stream.sender.as_ref(), &stream_id, &[stream.bump]];
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+
+        if let Some((_, route)) = route.as_ref().filter(|_| releasable > 0) {
+            distribute_tokens(
+                &route.splits,
+                releasable,
+                &stream.mint,
+                &ctx.accounts.vault.to_account_info(),
+                &stream.to_account_info(),
+                &token_program_info,
+                ctx.remaining_accounts,
+                &[signer_seeds],
+            )?;
+            record_payment_stats(&ctx.accounts.payment_stats, &stream.sender, releasable)?;
+        }
+
+        if unvested > 0 {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.vault.to_account_info(),
+                to: ctx.accounts.sender_token_account.to_account_info(),
+                authority: stream.to_account_info(),
+            };
+            token::transfer(CpiContext::new_with_signer(token_program_info.clone(), cpi_accounts, &[signer_seeds]), unvested)?;
+        }
+
+        let cpi_accounts = token::CloseAccount {
+            account: ctx.accounts.vault.to_account_info(),
+            destination: ctx.accounts.sender.to_account_info(),
+            authority: stream.to_account_info(),
+        };
+        token::close_account(CpiContext::new_with_signer(token_program_info, cpi_accounts, &[signer_seeds]))?;
+        release_obligation(&ctx.accounts.alias_account)?;
+
+        msg!("Stream cancelled for alias: {} ({} released, {} returned)", alias, releasable, unvested);
+        emit!(StreamCancelledEvent {
+            stream: stream.key(),
+            alias,
+            released: releasable,
+            returned: unvested,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Read-only preview of a payment. Performs no transfers; returns (via return data)
+    /// the exact per-recipient amounts and the `remaining_accounts` the payer must pass to
+    /// `execute_transfer` or `execute_token_transfer` (batches reject wSOL routes with `Native` splits).
+    /// Use `NATIVE_SOL_MINT` as `mint` to quote a native SOL `execute_transfer`.
+    /// A non-zero `referral_bps` is checked against the referral policy exactly as the payment by
+    /// `payer` would be; the referrer's share is quoted in `referral` and comes out of `amount`.
+    pub fn quote_payment(
+        ctx: Context<QuotePayment>,
+        _alias: String,
+        amount: u64,
+        mint: Pubkey,
+        referral_bps: u16,
+        payer: Pubkey,
+    ) -> Result<PaymentQuote> {
+        let decimals = if mint == NATIVE_SOL_MINT {
+            SOL_DECIMALS
+        } else {
+            ctx.accounts.token_mint.as_ref().ok_or(UnikError::MintMismatch)?.decimals
+        };
+        require_within_limits(&ctx.accounts.payment_limits, amount, decimals)?;
+        require_accepting_payments(&ctx.accounts.alias_account)?;
+        if mint != NATIVE_SOL_MINT {
+            require_mint_accepted(&ctx.accounts.mint_filter, &mint)?;
+        }
+
+        let referral = referral_share(
+            ctx.accounts.referral_policy.as_deref(),
+            ctx.accounts.referrer.as_ref().map(|referrer| referrer.key()),
+            &payer,
+            amount,
+            referral_bps,
+        )?
+        .map(|(referrer, referral_amount)| QuoteLeg {
+            recipient: referrer,
+            account: if mint == NATIVE_SOL_MINT { referrer } else { get_associated_token_address(&referrer, &mint) },
+            amount: referral_amount,
+        });
+        let split_total = amount - referral.as_ref().map_or(0, |leg| leg.amount);
+
+        let route = &ctx.accounts.route_account;
+        let splits = &route.splits;
+        let amounts = split_amounts(splits, split_total)?;
+
+        let legs = splits.iter().zip(amounts).enumerate().map(|(i, (split, split_amount))| {
+            let account = match route.sol_delivery_for(i) {
+                SolDelivery::Wrapped if mint == NATIVE_SOL_MINT => get_associated_token_address(&split.recipient, &native_mint::ID),
+                SolDelivery::Native if mint == native_mint::ID => split.recipient,
+                _ if mint == NATIVE_SOL_MINT => split.recipient,
+                _ => get_associated_token_address(&split.recipient, &mint),
+            };
+            QuoteLeg {
+                recipient: split.recipient,
+                account,
+                amount: split_amount,
+            }
+        }).collect();
+
+        Ok(PaymentQuote {
+            mint,
+            amount,
+            fee: 0, // No protocol fee is charged on payments
+            legs,
+            referral,
+            route_hash: route.splits_hash()?,
+        })
+    }
+
+    /// Set the minimum (and optional maximum) payment accepted for a mint (owner only).
+    /// Use `NATIVE_SOL_MINT` for SOL payments. Without limits the protocol default applies.
+    pub fn set_payment_limits(ctx: Context<SetPaymentLimits>, alias: String, mint: Pubkey, min_amount: u64, max_amount: Option<u64>) -> Result<()> {
+        require!(min_amount > 0, UnikError::InvalidPaymentLimits);
+        if let Some(max) = max_amount {
+            require!(max >= min_amount, UnikError::InvalidPaymentLimits);
+        }
+
+        let payment_limits = &mut ctx.accounts.payment_limits;
+        payment_limits.alias_ref = ctx.accounts.alias_account.key();
+        payment_limits.mint = mint;
+        payment_limits.min_amount = min_amount;
+        payment_limits.max_amount = max_amount;
+        payment_limits.bump = ctx.bumps.payment_limits;
+        payment_limits.schema_version = PaymentLimits::SCHEMA_VERSION;
+
+        msg!("Payment limits set for alias: {} mint: {}", alias, mint);
+        emit!(PaymentLimitsEvent {
+            alias: alias.clone(),
+            mint,
+            min_amount,
+            max_amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Remove the payment limits for a mint - refunds rent to owner
+    pub fn delete_payment_limits(_ctx: Context<DeletePaymentLimits>, alias: String, mint: Pubkey) -> Result<()> {
+        msg!("Payment limits deleted for alias: {} mint: {}", alias, mint);
+        Ok(())
+    }
+
+    /// Pin the price feed used to convert `currency` amounts to SOL for this alias (owner only).
+    /// `price_feed` must be a Pyth-format price account quoting SOL in `currency`.
+    pub fn set_fiat_price_feed(
+        ctx: Context<SetFiatPriceFeed>,
+        alias: String,
+        currency: [u8; 3],
+        price_feed: Pubkey,
+        max_staleness: i64,
+        max_confidence_bps: u16,
+    ) -> Result<()> {
+        require!(max_staleness > 0 && max_confidence_bps <= 10000, UnikError::InvalidPriceFeedConfig);
+        require!(currency.iter().all(u8::is_ascii_uppercase), UnikError::InvalidCurrency);
+
+        let fiat_feed = &mut ctx.accounts.fiat_feed;
+        fiat_feed.alias_ref = ctx.accounts.alias_account.key();
+        fiat_feed.currency = currency;
+        fiat_feed.price_feed = price_feed;
+        fiat_feed.max_staleness = max_staleness;
+        fiat_feed.max_confidence_bps = max_confidence_bps;
+        fiat_feed.bump = ctx.bumps.fiat_feed;
+        fiat_feed.schema_version = FiatPriceFeed::SCHEMA_VERSION;
+
+        msg!("Fiat price feed for {} set to {} for alias: {}", String::from_utf8_lossy(&currency), price_feed, alias);
+        Ok(())
+    }
+
+    /// Allow payers to route up to `max_bps` of a payment to a referrer (owner only).
+    /// With `open` any referrer is accepted; otherwise only `allowed_referrers`.
+    pub fn set_referral_policy(
+        ctx: Context<SetReferralPolicy>,
+        alias: String,
+        max_bps: u16,
+        open: bool,
+        allowed_referrers: Vec<Pubkey>,
+    ) -> Result<()> {
+        require!(max_bps > 0 && max_bps <= 10000, UnikError::InvalidReferralPolicy);
+        require!(allowed_referrers.len() <= MAX_REFERRERS, UnikError::InvalidReferralPolicy);
+        require!(open || !allowed_referrers.is_empty(), UnikError::InvalidReferralPolicy);
+
+        let referral_policy = &mut ctx.accounts.referral_policy;
+        referral_policy.alias_ref = ctx.accounts.alias_account.key();
+        referral_policy.max_bps = max_bps;
+        referral_policy.open = open;
+        referral_policy.allowed_referrers = allowed_referrers;
+        referral_policy.bump = ctx.bumps.referral_policy;
+        referral_policy.schema_version = ReferralPolicy::SCHEMA_VERSION;
+
+        msg!("Referral policy set for alias: {} (max {} bps, open: {})", alias, max_bps, open);
+        Ok(())
+    }
+
+    /// Disable referrals for the alias - refunds rent to owner
+    pub fn delete_referral_policy(_ctx: Context<DeleteReferralPolicy>, alias: String) -> Result<()> {
+        msg!("Referral policy deleted for alias: {}", alias);
+        Ok(())
+    }
+
+    /// Restrict which SPL mints the alias accepts (owner only): only `mints` in Allow mode,
+    /// anything but `mints` in Deny mode. Native SOL payments are unaffected.
+    pub fn set_mint_filter(ctx: Context<SetMintFilter>, alias: String, mode: MintFilterMode, mints: Vec<Pubkey>) -> Result<()> {
+        require!(mints.len() <= MAX_FILTER_MINTS, UnikError::TooManyFilterMints);
+
+        let mint_filter = &mut ctx.accounts.mint_filter;
+        mint_filter.alias_ref = ctx.accounts.alias_account.key();
+        mint_filter.mode = mode;
+        mint_filter.mints = mints;
+        mint_filter.bump = ctx.bumps.mint_filter;
+        mint_filter.schema_version = MintFilter::SCHEMA_VERSION;
+
+        msg!("Mint filter set for alias: {} ({:?}, {} mints)", alias, mode, mint_filter.mints.len());
+        emit!(MintFilterEvent {
+            alias,
+            mode: Some(mode),
+            mints: mint_filter.mints.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Accept any mint again - refunds rent to owner
+    pub fn delete_mint_filter(_ctx: Context<DeleteMintFilter>, alias: String) -> Result<()> {
+        msg!("Mint filter deleted for alias: {}", alias);
+        emit!(MintFilterEvent {
+            alias,
+            mode: None,
+            mints: Vec::new(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Start on-chain receipt statistics for a mint (owner only).
+    /// From then on every transfer instruction updates the PaymentStats PDA.
+    pub fn init_payment_stats(ctx: Context<InitPaymentStats>, alias: String, mint: Pubkey) -> Result<()> {
+        let payment_stats = &mut ctx.accounts.payment_stats;
+        payment_stats.alias_ref = ctx.accounts.alias_account.key();
+        payment_stats.mint = mint;
+        payment_stats.tracking_since = Clock::get()?.unix_timestamp;
+        payment_stats.bump = ctx.bumps.payment_stats;
+        payment_stats.schema_version = PaymentStats::SCHEMA_VERSION;
+
+        msg!("Payment stats initialized for alias: {} mint: {}", alias, mint);
         Ok(())
     }
 
@@ -219,16 +1178,18 @@ pub mod unik_anchor {
         require!(new_metadata_uri.len() <= 200, UnikError::MetadataTooLong);
         
         let alias_account = &mut ctx.accounts.alias_account;
-        alias_account.metadata_uri = new_metadata_uri;
+        let old_metadata_uri = std::mem::replace(&mut alias_account.metadata_uri, new_metadata_uri);
         
         // LOW-07: Increment version on update
         alias_account.version = alias_account.version.checked_add(1).unwrap_or(alias_account.version);
         
         msg!("Alias metadata updated to version {}", alias_account.version);
-        emit!(AliasEvent {
-            event_type: "UPDATE".to_string(),
+        emit!(AliasMetadataUpdatedEvent {
             alias: alias_account.alias.clone(),
             owner: alias_account.owner,
+            old_metadata_uri,
+            new_metadata_uri: alias_account.metadata_uri.clone(),
+            version: alias_account.version,
             timestamp: Clock::get()?.unix_timestamp,
         });
         Ok(())
@@ -247,8 +1208,7 @@ pub mod unik_anchor {
         alias_account.is_active = false;
         
         msg!("Alias deactivated: {}", alias_account.alias);
-        emit!(AliasEvent {
-            event_type: "DEACTIVATE".to_string(),
+        emit!(AliasDeactivatedEvent {
             alias: alias_account.alias.clone(),
             owner: alias_account.owner,
             timestamp: Clock::get()?.unix_timestamp,
@@ -260,12 +1220,13 @@ pub mod unik_anchor {
     pub fn reactivate_alias(ctx: Context<UpdateAlias>, _alias: String) -> Result<()> {
         let alias_account = &mut ctx.accounts.alias_account;
         require!(!alias_account.is_active, UnikError::AliasAlreadyActive);
+        // A compliance freeze can only be lifted by the compliance authority
+        require!(alias_account.freeze_reason == 0, UnikError::AliasFrozen);
         
         alias_account.is_active = true;
         
         msg!("Alias reactivated: {}", alias_account.alias);
-        emit!(AliasEvent {
-            event_type: "REACTIVATE".to_string(),
+        emit!(AliasReactivatedEvent {
             alias: alias_account.alias.clone(),
             owner: alias_account.owner,
             timestamp: Clock::get()?.unix_timestamp,
@@ -273,17 +1234,822 @@ pub mod unik_anchor {
         Ok(())
     }
 
-    /// Migrate a stale route account that can't be deserialized with the current schema.
-    /// Uses UncheckedAccount to bypass Anchor deserialization.
-    /// Only the alias owner can call this. Closes the old account and refunds rent.
-    pub fn migrate_route_account(ctx: Context<MigrateRouteAccount>, alias: String) -> Result<()> {
-        let alias_account = &ctx.accounts.alias_account;
-        require!(alias_account.owner == ctx.accounts.user.key(), UnikError::Unauthorized);
+    /// Create the protocol config. Only the program's upgrade authority can do this, and it becomes
+    /// the config authority; `pause_authority` can then toggle the pause flags.
+    pub fn init_config(ctx: Context<InitConfig>, pause_authority: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.authority = ctx.accounts.authority.key();
+        config.pause_authority = pause_authority;
+        config.paused_flags = 0;
+        config.bump = ctx.bumps.config;
+        config.schema_version = Config::SCHEMA_VERSION;
+        config.compliance_authority = Pubkey::default();
+        config.treasury = Pubkey::default();
+        config.royalty_bps = 0;
 
-        // Validate the route_account PDA manually
-        let (expected_route_pda, _bump) = Pubkey::find_program_address(
-            &[b"route", alias.as_bytes()],
-            ctx.program_id,
+        msg!("Config initialized with pause authority: {}", pause_authority);
+        emit!(ConfigAuthoritiesEvent {
+            authority: config.authority,
+            pause_authority,
+            compliance_authority: config.compliance_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Hand over the config, pause or compliance authority (config authority only).
+    /// `Pubkey::default()` as compliance authority disables alias freezes.
+    pub fn set_config_authorities(
+        ctx: Context<UpdateConfig>,
+        authority: Pubkey,
+        pause_authority: Pubkey,
+        compliance_authority: Pubkey,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(config.authority == ctx.accounts.signer.key(), UnikError::Unauthorized);
+        config.authority = authority;
+        config.pause_authority = pause_authority;
+        config.compliance_authority = compliance_authority;
+
+        msg!(
+            "Config authorities updated: authority {}, pause authority {}, compliance authority {}",
+            authority, pause_authority, compliance_authority
+        );
+        emit!(ConfigAuthoritiesEvent {
+            authority,
+            pause_authority,
+            compliance_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Set the protocol royalty taken from alias marketplace sales and the wallet receiving it
+    /// (config authority only). `royalty_bps = 0` or a default treasury disables the royalty.
+    pub fn set_marketplace_fee(ctx: Context<UpdateConfig>, treasury: Pubkey, royalty_bps: u16) -> Result<()> {
+        require!(royalty_bps <= MAX_ROYALTY_BPS, UnikError::InvalidRoyalty);
+        let config = &mut ctx.accounts.config;
+        require!(config.authority == ctx.accounts.signer.key(), UnikError::Unauthorized);
+        config.treasury = treasury;
+        config.royalty_bps = royalty_bps;
+
+        msg!("Marketplace royalty set to {} bps, treasury {}", royalty_bps, treasury);
+        emit!(MarketplaceFeeEvent {
+            treasury,
+            royalty_bps,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Replace the pause flags (PAUSE_* bits). The pause authority or the config authority may call this.
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused_flags: u8) -> Result<()> {
+        require!(paused_flags & !PAUSE_ALL == 0, UnikError::InvalidPauseFlags);
+        let config = &mut ctx.accounts.config;
+        let signer = ctx.accounts.signer.key();
+        require!(signer == config.pause_authority || signer == config.authority, UnikError::Unauthorized);
+
+        let previous_flags = config.paused_flags;
+        config.paused_flags = paused_flags;
+
+        msg!("Pause flags changed from {:#06b} to {:#06b}", previous_flags, paused_flags);
+        emit!(PauseEvent {
+            authority: signer,
+            previous_flags,
+            paused_flags,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Freeze an alias pending compliance review (compliance authority only): payments to it fail and
+    /// the owner can't reactivate or delete it until `unfreeze_alias`. `reason_code` is recorded on-chain.
+    pub fn freeze_alias(ctx: Context<ComplianceFreeze>, alias: String, reason_code: u16) -> Result<()> {
+        require!(reason_code != 0, UnikError::InvalidFreezeReason);
+        let alias_account = &mut ctx.accounts.alias_account;
+        let now = Clock::get()?.unix_timestamp;
+        alias_account.freeze_reason = reason_code;
+        alias_account.frozen_at = now;
+        alias_account.frozen_by = ctx.accounts.authority.key();
+        // The account was grown to the current layout by the realloc constraint
+        alias_account.schema_version = AliasAccount::SCHEMA_VERSION;
+
+        msg!("Alias frozen: {} (reason {})", alias, reason_code);
+        emit!(AliasFreezeEvent {
+            alias,
+            frozen: true,
+            reason_code,
+            authority: ctx.accounts.authority.key(),
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Lift a compliance freeze (compliance authority only)
+    pub fn unfreeze_alias(ctx: Context<ComplianceFreeze>, alias: String) -> Result<()> {
+        let alias_account = &mut ctx.accounts.alias_account;
+        require!(alias_account.freeze_reason != 0, UnikError::AliasNotFrozen);
+        let reason_code = alias_account.freeze_reason;
+        alias_account.freeze_reason = 0;
+        alias_account.frozen_at = 0;
+        alias_account.frozen_by = Pubkey::default();
+        alias_account.schema_version = AliasAccount::SCHEMA_VERSION;
+
+        msg!("Alias unfrozen: {}", alias);
+        emit!(AliasFreezeEvent {
+            alias,
+            frozen: false,
+            reason_code,
+            authority: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Mint the alias as a 1-of-1 Token-2022 NFT to the owner (owner only). The metadata extension
+    /// makes it show up in wallets; the transfer hook is this program, so whoever receives the NFT
+    /// becomes `AliasAccount.owner` and passes every owner-gated instruction. Reverted by `detokenize_alias`.
+    pub fn tokenize_alias(ctx: Context<TokenizeAlias>, alias: String) -> Result<()> {
+        let alias_account = &ctx.accounts.alias_account;
+        require!(alias_account.nft_mint == Pubkey::default(), UnikError::AliasAlreadyTokenized);
+        require!(alias_account.freeze_reason == 0, UnikError::AliasFrozen);
+
+        let alias_key = alias_account.key();
+        let alias_bump = [ctx.bumps.alias_account];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"alias", alias.as_bytes(), &alias_bump]];
+        let mint_info = ctx.accounts.nft_mint.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+
+        // Token-2022 grows the mint for the metadata but doesn't fund the extra rent
+        let metadata = TokenMetadata {
+            update_authority: OptionalNonZeroPubkey::try_from(Some(alias_key))?,
+            mint: mint_info.key(),
+            name: alias.clone(),
+            symbol: ALIAS_NFT_SYMBOL.to_string(),
+            uri: alias_account.metadata_uri.clone(),
+            additional_metadata: Vec::new(),
+        };
+        let rent_due = Rent::get()?
+            .minimum_balance(mint_info.data_len() + metadata.tlv_size_of()?)
+            .saturating_sub(mint_info.lamports());
+        if rent_due > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.user.to_account_info(),
+                        to: mint_info.clone(),
+                    },
+                ),
+                rent_due,
+            )?;
+        }
+        token_interface::token_metadata_initialize(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                token_interface::TokenMetadataInitialize {
+                    token_program_id: token_program.clone(),
+                    metadata: mint_info.clone(),
+                    update_authority: alias_account.to_account_info(),
+                    mint_authority: alias_account.to_account_info(),
+                    mint: mint_info.clone(),
+                },
+                signer_seeds,
+            ),
+            metadata.name,
+            metadata.symbol,
+            metadata.uri,
+        )?;
+
+        // The alias PDA stays mint authority, but only mints here and only while untokenized
+        token_interface::mint_to(
+            CpiContext::new_with_signer(
+                token_program,
+                token_interface::MintTo {
+                    mint: mint_info.clone(),
+                    to: ctx.accounts.holder_token_account.to_account_info(),
+                    authority: alias_account.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            1,
+        )?;
+
+        // Token-2022 passes the alias and route accounts (writable) to the transfer hook
+        let (route_key, _) = Pubkey::find_program_address(&[b"route", alias.as_bytes()], ctx.program_id);
+        ExtraAccountMetaList::init::<ExecuteInstruction>(
+            &mut ctx.accounts.extra_account_meta_list.try_borrow_mut_data()?,
+            &[
+                ExtraAccountMeta::new_with_pubkey(&alias_key, false, true)?,
+                ExtraAccountMeta::new_with_pubkey(&route_key, false, true)?,
+            ],
+        )?;
+
+        let alias_account = &mut ctx.accounts.alias_account;
+        alias_account.nft_mint = mint_info.key();
+        // The account was grown to the current layout by the realloc constraint
+        alias_account.schema_version = AliasAccount::SCHEMA_VERSION;
+
+        msg!("Alias tokenized: {} (mint {})", alias, alias_account.nft_mint);
+        emit!(AliasTokenizeEvent {
+            alias,
+            mint: alias_account.nft_mint,
+            holder: alias_account.owner,
+            tokenized: true,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Burn the alias NFT and close its mint (holder only - the holder is the alias owner).
+    /// The alias keeps its current owner and is managed directly again.
+    pub fn detokenize_alias(ctx: Context<DetokenizeAlias>, alias: String) -> Result<()> {
+        let alias_bump = [ctx.bumps.alias_account];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"alias", alias.as_bytes(), &alias_bump]];
+        let token_program = ctx.accounts.token_program.to_account_info();
+
+        token_interface::burn(
+            CpiContext::new(
+                token_program.clone(),
+                token_interface::Burn {
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    from: ctx.accounts.holder_token_account.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+        token_interface::close_account(CpiContext::new_with_signer(
+            token_program,
+            token_interface::CloseAccount {
+                account: ctx.accounts.nft_mint.to_account_info(),
+                destination: ctx.accounts.user.to_account_info(),
+                authority: ctx.accounts.alias_account.to_account_info(),
+            },
+            signer_seeds,
+        ))?;
+        close_program_account(&ctx.accounts.extra_account_meta_list, &ctx.accounts.user)?;
+
+        let alias_account = &mut ctx.accounts.alias_account;
+        let mint = std::mem::take(&mut alias_account.nft_mint);
+
+        msg!("Alias detokenized: {}", alias);
+        emit!(AliasTokenizeEvent {
+            alias,
+            mint,
+            holder: alias_account.owner,
+            tokenized: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Transfer hook of alias NFTs, invoked by Token-2022 on every transfer: the recipient's wallet
+    /// becomes the alias owner and `version` is bumped, as for any owner change. As with a sale,
+    /// the route is reset to pay the new owner in full.
+    #[interface(spl_transfer_hook_interface::execute)]
+    pub fn transfer_hook(ctx: Context<TransferHook>, amount: u64) -> Result<()> {
+        // Only set by Token-2022 during a transfer, so the hook can't be called directly to take the alias
+        {
+            let source = ctx.accounts.source_token.to_account_info();
+            let data = source.try_borrow_data()?;
+            let source = StateWithExtensions::<token_interface::spl_token_2022::state::Account>::unpack(&data)?;
+            require!(bool::from(source.get_extension::<TransferHookAccount>()?.transferring), UnikError::InvalidNftTransfer);
+        }
+        require!(amount == 1, UnikError::InvalidNftTransfer);
+
+        let alias_account = &mut ctx.accounts.alias_account;
+        require!(alias_account.freeze_reason == 0, UnikError::AliasFrozen);
+        transfer_alias(alias_account, &ctx.accounts.route_account, ctx.accounts.destination_token.owner)?;
+
+        msg!("Alias NFT transferred: {} (version {})", alias_account.alias, alias_account.version);
+        Ok(())
+    }
+
+    /// Offer the alias for sale at `price` in `mint` (NATIVE_SOL_MINT for lamports); owner only.
+    /// Listing again replaces the price. Tokenized aliases trade as NFTs instead.
+    /// A listing left by a previous owner is withdrawn first, its rent going back to that seller.
+    pub fn list_alias(ctx: Context<ListAlias>, alias: String, price: u64, mint: Pubkey) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_MARKETPLACE)?;
+        require!(price > 0, UnikError::InvalidPrice);
+        require_transferable(&ctx.accounts.alias_account)?;
+
+        let stale = &ctx.accounts.listing;
+        if stale.seller != Pubkey::default() && stale.seller != ctx.accounts.user.key() {
+            let seller = ctx.accounts.listing_seller.as_ref().ok_or(UnikError::InvalidRecipientAccount)?;
+            require!(seller.key() == stale.seller, UnikError::InvalidRecipientAccount);
+            // The new seller funds the listing, and the previous one gets back what they paid
+            let rent = stale.get_lamports();
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.user.to_account_info(),
+                        to: stale.to_account_info(),
+                    },
+                ),
+                rent,
+            )?;
+            stale.sub_lamports(rent)?;
+            seller.add_lamports(rent)?;
+            emit!(ListingEvent {
+                alias: alias.clone(),
+                seller: stale.seller,
+                mint: stale.mint,
+                price: stale.price,
+                listed: false,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        }
+
+        let listing = &mut ctx.accounts.listing;
+        listing.alias_ref = ctx.accounts.alias_account.key();
+        listing.seller = ctx.accounts.user.key();
+        listing.mint = mint;
+        listing.price = price;
+        listing.created_at = Clock::get()?.unix_timestamp;
+        listing.bump = ctx.bumps.listing;
+        listing.schema_version = Listing::SCHEMA_VERSION;
+
+        msg!("Alias listed: {} for {} (mint {})", alias, price, mint);
+        emit!(ListingEvent {
+            alias,
+            seller: listing.seller,
+            mint,
+            price,
+            listed: true,
+            timestamp: listing.created_at,
+        });
+        Ok(())
+    }
+
+    /// Withdraw a listing - refunds rent to the seller
+    pub fn delist_alias(ctx: Context<DelistAlias>, alias: String) -> Result<()> {
+        let listing = &ctx.accounts.listing;
+        msg!("Alias delisted: {}", alias);
+        emit!(ListingEvent {
+            alias,
+            seller: listing.seller,
+            mint: listing.mint,
+            price: listing.price,
+            listed: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Buy a listed alias at its price in one step: the seller is paid (less the protocol royalty),
+    /// ownership moves to the buyer and the route is reset to pay the buyer.
+    /// `price` must match the listing, so a price raised after the buyer saw it fails.
+    pub fn buy_alias(ctx: Context<BuyAlias>, alias: String, price: u64) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_MARKETPLACE)?;
+        let listing = &ctx.accounts.listing;
+        require!(listing.price == price, UnikError::ListingChanged);
+        // A listing doesn't survive an ownership change, e.g. through the alias NFT
+        require!(ctx.accounts.alias_account.owner == listing.seller, UnikError::ListingChanged);
+        require!(ctx.accounts.buyer.key() != listing.seller, UnikError::InvalidBuyer);
+        require_transferable(&ctx.accounts.alias_account)?;
+
+        let config = load_config(&ctx.accounts.config)?;
+        let royalty = config.as_ref().map_or(Ok(0), |config| config.royalty(price))?;
+        let proceeds = price.checked_sub(royalty).ok_or(UnikError::Overflow)?;
+
+        if listing.mint == NATIVE_SOL_MINT {
+            let mut legs = vec![(ctx.accounts.seller.to_account_info(), proceeds)];
+            if royalty > 0 {
+                let treasury = ctx.accounts.treasury.as_ref().ok_or(UnikError::InvalidTreasury)?;
+                require!(config.as_ref().map(|config| config.treasury) == Some(treasury.key()), UnikError::InvalidTreasury);
+                legs.push((treasury.to_account_info(), royalty));
+            }
+            for (to, amount) in legs {
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer { from: ctx.accounts.buyer.to_account_info(), to },
+                    ),
+                    amount,
+                )?;
+            }
+        } else {
+            let token_program = ctx.accounts.token_program.as_ref().ok_or(UnikError::MissingTokenAccount)?;
+            let buyer_token_account = ctx.accounts.buyer_token_account.as_ref().ok_or(UnikError::MissingTokenAccount)?;
+            let seller_token_account = ctx.accounts.seller_token_account.as_ref().ok_or(UnikError::MissingTokenAccount)?;
+            let mut legs = vec![(seller_token_account.to_account_info(), proceeds)];
+            if royalty > 0 {
+                let treasury_token_account = ctx.accounts.treasury_token_account.as_ref().ok_or(UnikError::InvalidTreasury)?;
+                require!(
+                    config.as_ref().map(|config| config.treasury) == Some(treasury_token_account.owner),
+                    UnikError::InvalidTreasury
+                );
+                legs.push((treasury_token_account.to_account_info(), royalty));
+            }
+            for (to, amount) in legs {
+                let cpi_accounts = Transfer {
+                    from: buyer_token_account.to_account_info(),
+                    to,
+                    authority: ctx.accounts.buyer.to_account_info(),
+                };
+                token::transfer(CpiContext::new(token_program.to_account_info(), cpi_accounts), amount)?;
+            }
+        }
+
+        let seller = listing.seller;
+        let mint = listing.mint;
+        let buyer = ctx.accounts.buyer.key();
+        transfer_alias(&mut ctx.accounts.alias_account, &ctx.accounts.route_account, buyer)?;
+
+        msg!("Alias sold: {} for {} ({} royalty)", alias, price, royalty);
+        emit!(AliasSoldEvent {
+            alias,
+            seller,
+            buyer,
+            mint,
+            price,
+            royalty,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Offer `amount` lamports for an alias; the lamports are held by the Offer PDA until the
+    /// owner accepts (`accept_offer`) or the buyer withdraws (`cancel_offer`).
+    pub fn make_offer(ctx: Context<MakeOffer>, alias: String, amount: u64) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_MARKETPLACE)?;
+        require!(amount > 0, UnikError::InvalidPrice);
+        add_obligation(&mut ctx.accounts.alias_account)?;
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.buyer.to_account_info(),
+                to: ctx.accounts.offer.to_account_info(),
+            }
+        );
+        system_program::transfer(cpi_context, amount)?;
+
+        let offer = &mut ctx.accounts.offer;
+        offer.alias_ref = ctx.accounts.alias_account.key();
+        offer.buyer = ctx.accounts.buyer.key();
+        offer.mint = NATIVE_SOL_MINT;
+        offer.amount = amount;
+        offer.created_at = Clock::get()?.unix_timestamp;
+        offer.bump = ctx.bumps.offer;
+        offer.schema_version = Offer::SCHEMA_VERSION;
+
+        msg!("Offer of {} lamports for alias: {}", amount, alias);
+        emit!(OfferEvent {
+            alias,
+            buyer: offer.buyer,
+            mint: offer.mint,
+            amount,
+            open: true,
+            timestamp: offer.created_at,
+        });
+        Ok(())
+    }
+
+    /// SPL token counterpart of `make_offer`; funds are held in a vault owned by the Offer PDA.
+    pub fn make_token_offer(ctx: Context<MakeTokenOffer>, alias: String, amount: u64) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_MARKETPLACE)?;
+        require!(amount > 0, UnikError::InvalidPrice);
+        add_obligation(&mut ctx.accounts.alias_account)?;
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.buyer_token_account.to_account_info(),
+            to: ctx.accounts.vault.to_account_info(),
+            authority: ctx.accounts.buyer.to_account_info(),
+        };
+        token::transfer(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts), amount)?;
+
+        let offer = &mut ctx.accounts.offer;
+        offer.alias_ref = ctx.accounts.alias_account.key();
+        offer.buyer = ctx.accounts.buyer.key();
+        offer.mint = ctx.accounts.mint.key();
+        offer.amount = amount;
+        offer.created_at = Clock::get()?.unix_timestamp;
+        offer.bump = ctx.bumps.offer;
+        offer.schema_version = Offer::SCHEMA_VERSION;
+
+        msg!("Offer of {} tokens for alias: {}", amount, alias);
+        emit!(OfferEvent {
+            alias,
+            buyer: offer.buyer,
+            mint: offer.mint,
+            amount,
+            open: true,
+            timestamp: offer.created_at,
+        });
+        Ok(())
+    }
+
+    /// Withdraw an offer (buyer), or decline it (alias owner); the escrowed funds and rent go back
+    /// to the buyer. Never paused.
+    pub fn cancel_offer(ctx: Context<CancelOffer>, alias: String) -> Result<()> {
+        let offer = &ctx.accounts.offer;
+        // The alias may have been deleted, or registered again by someone else, since the offer was made
+        let alias_owner = load_alias(&ctx.accounts.alias_account)?
+            .filter(|alias_account| alias_account.registered_at <= offer.created_at)
+            .map(|alias_account| alias_account.owner);
+        let authority = ctx.accounts.authority.key();
+        require!(authority == offer.buyer || Some(authority) == alias_owner, UnikError::Unauthorized);
+        if alias_owner.is_some() {
+            release_obligation(&ctx.accounts.alias_account)?;
+        }
+        let signer_seeds: &[&[u8]] = &[b"offer", alias.as_bytes(), offer.buyer.as_ref(), &[offer.bump]];
+
+        if offer.mint != NATIVE_SOL_MINT {
+            let (vault, token_program) = offer_vault(&ctx.accounts.vault, &ctx.accounts.token_program)?;
+            let buyer_token_account = ctx.accounts.buyer_token_account.as_ref().ok_or(UnikError::MissingTokenAccount)?;
+            let cpi_accounts = Transfer {
+                from: vault.to_account_info(),
+                to: buyer_token_account.to_account_info(),
+                authority: offer.to_account_info(),
+            };
+            token::transfer(
+                CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, &[signer_seeds]),
+                offer.amount,
+            )?;
+            close_offer_vault(offer, vault, token_program, &ctx.accounts.buyer, signer_seeds)?;
+        }
+        // Escrowed lamports leave with the Offer account itself (`close = buyer`)
+
+        msg!("Offer cancelled for alias: {}", alias);
+        emit!(OfferEvent {
+            alias,
+            buyer: offer.buyer,
+            mint: offer.mint,
+            amount: offer.amount,
+            open: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Sell the alias to an offer's buyer (owner only): the escrowed funds go to the owner, less the
+    /// protocol royalty, and ownership and route move to the buyer. `mint` and `amount` must match
+    /// the offer, so one withdrawn and re-made for less can't be accepted by accident.
+    pub fn accept_offer(ctx: Context<SettleOffer>, alias: String, mint: Pubkey, amount: u64) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_MARKETPLACE)?;
+        require!(ctx.accounts.authority.key() == ctx.accounts.alias_account.owner, UnikError::Unauthorized);
+        let offer = &ctx.accounts.offer;
+        require!(offer.mint == mint && offer.amount == amount, UnikError::OfferChanged);
+        require!(offer.buyer != ctx.accounts.authority.key(), UnikError::InvalidBuyer);
+        require_transferable(&ctx.accounts.alias_account)?;
+
+        let config = load_config(&ctx.accounts.config)?;
+        let royalty = config.as_ref().map_or(Ok(0), |config| config.royalty(amount))?;
+        let proceeds = amount.checked_sub(royalty).ok_or(UnikError::Overflow)?;
+        let signer_seeds: &[&[u8]] = &[b"offer", alias.as_bytes(), offer.buyer.as_ref(), &[offer.bump]];
+
+        if mint == NATIVE_SOL_MINT {
+            offer.sub_lamports(proceeds)?;
+            ctx.accounts.authority.add_lamports(proceeds)?;
+            if royalty > 0 {
+                let treasury = ctx.accounts.treasury.as_ref().ok_or(UnikError::InvalidTreasury)?;
+                require!(config.as_ref().map(|config| config.treasury) == Some(treasury.key()), UnikError::InvalidTreasury);
+                offer.sub_lamports(royalty)?;
+                treasury.add_lamports(royalty)?;
+            }
+        } else {
+            let (vault, token_program) = offer_vault(&ctx.accounts.vault, &ctx.accounts.token_program)?;
+            let seller_token_account = ctx.accounts.seller_token_account.as_ref().ok_or(UnikError::MissingTokenAccount)?;
+            let mut legs = vec![(seller_token_account.to_account_info(), proceeds)];
+            if royalty > 0 {
+                let treasury_token_account = ctx.accounts.treasury_token_account.as_ref().ok_or(UnikError::InvalidTreasury)?;
+                require!(
+                    config.as_ref().map(|config| config.treasury) == Some(treasury_token_account.owner),
+                    UnikError::InvalidTreasury
+                );
+                legs.push((treasury_token_account.to_account_info(), royalty));
+            }
+            for (to, leg) in legs {
+                let cpi_accounts = Transfer {
+                    from: vault.to_account_info(),
+                    to,
+                    authority: offer.to_account_info(),
+                };
+                token::transfer(
+                    CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, &[signer_seeds]),
+                    leg,
+                )?;
+            }
+            close_offer_vault(offer, vault, token_program, &ctx.accounts.buyer, signer_seeds)?;
+        }
+
+        // The owner's own listing is void now; one left by a previous owner stays theirs to delist
+        let seller = ctx.accounts.authority.key();
+        if let Some(listing) = load_optional::<Listing>(&ctx.accounts.listing)? {
+            if listing.seller == seller {
+                close_program_account(&ctx.accounts.listing, &ctx.accounts.authority)?;
+            }
+        }
+
+        let buyer = offer.buyer;
+        let alias_account = &mut ctx.accounts.alias_account;
+        alias_account.open_obligations = alias_account.open_obligations.saturating_sub(1);
+        transfer_alias(alias_account, &ctx.accounts.route_account, buyer)?;
+
+        msg!("Offer accepted for alias: {} at {} ({} royalty)", alias, amount, royalty);
+        emit!(AliasSoldEvent {
+            alias,
+            seller,
+            buyer,
+            mint,
+            price: amount,
+            royalty,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Put an unregistered alias up for an English auction in lamports (config authority only).
+    /// Bidding opens at `start_ts` and closes `duration` seconds later, extended so that a bid always
+    /// leaves at least `extension` seconds for counter-bids. Proceeds go to the protocol treasury.
+    /// Registrations don't expire, so only names never registered (or since deleted) can be auctioned;
+    /// there is no path to reclaim a registered alias from its owner.
+    pub fn create_auction(
+        ctx: Context<CreateAuction>,
+        alias: String,
+        reserve_price: u64,
+        start_ts: i64,
+        duration: i64,
+        extension: i64,
+    ) -> Result<()> {
+        require_valid_alias(&alias)?;
+        require!(ctx.accounts.alias_account.data_is_empty(), UnikError::AliasAlreadyRegistered);
+        require!(reserve_price > 0, UnikError::InvalidPrice);
+        require!(duration > 0 && extension >= 0, UnikError::InvalidAuctionSchedule);
+        // Settlement pays the treasury; without one the winning bid would have nowhere to go
+        require!(ctx.accounts.config.treasury != Pubkey::default(), UnikError::InvalidTreasury);
+
+        let auction = &mut ctx.accounts.auction;
+        auction.alias = alias.clone();
+        auction.creator = ctx.accounts.authority.key();
+        auction.reserve_price = reserve_price;
+        auction.start_ts = start_ts;
+        auction.end_ts = start_ts.checked_add(duration).ok_or(UnikError::Overflow)?;
+        auction.extension = extension;
+        auction.highest_bidder = Pubkey::default();
+        auction.highest_bid = 0;
+        auction.bump = ctx.bumps.auction;
+        auction.schema_version = Auction::SCHEMA_VERSION;
+
+        msg!("Auction created for alias: {} (reserve {} lamports)", alias, reserve_price);
+        emit!(AuctionCreatedEvent {
+            alias,
+            reserve_price,
+            start_ts,
+            end_ts: auction.end_ts,
+            extension,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Bid `amount` lamports, held by the Auction PDA. The bid must meet the reserve and beat the
+    /// current one by MIN_BID_INCREMENT_BPS; the outbid bidder is refunded in the same instruction.
+    pub fn place_bid(ctx: Context<PlaceBid>, alias: String, amount: u64) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_MARKETPLACE)?;
+        let now = Clock::get()?.unix_timestamp;
+        let auction = &ctx.accounts.auction;
+        require!(now >= auction.start_ts && now < auction.end_ts, UnikError::AuctionNotOpen);
+        require!(amount >= auction.min_bid()?, UnikError::BidTooLow);
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.bidder.to_account_info(),
+                    to: auction.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        if auction.highest_bidder != Pubkey::default() {
+            let previous_bidder = ctx.accounts.previous_bidder.as_ref().ok_or(UnikError::InvalidRecipientAccount)?;
+            require!(previous_bidder.key() == auction.highest_bidder, UnikError::InvalidRecipientAccount);
+            auction.sub_lamports(auction.highest_bid)?;
+            previous_bidder.add_lamports(auction.highest_bid)?;
+        }
+
+        let auction = &mut ctx.accounts.auction;
+        auction.highest_bidder = ctx.accounts.bidder.key();
+        auction.highest_bid = amount;
+        // Anti-sniping: a late bid pushes the close back so others can respond
+        let min_end = now.checked_add(auction.extension).ok_or(UnikError::Overflow)?;
+        auction.end_ts = auction.end_ts.max(min_end);
+
+        msg!("Bid of {} lamports on alias: {} (ends {})", amount, alias, auction.end_ts);
+        emit!(BidEvent {
+            alias,
+            bidder: auction.highest_bidder,
+            amount,
+            end_ts: auction.end_ts,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Close an auction once it has ended. With a winning bid the alias is registered to the winner,
+    /// as `register_alias` would (empty metadata URI, to be set with `update_alias_metadata`), and the
+    /// bid goes to the treasury. Permissionless; `payer` funds the new alias account.
+    pub fn settle_auction(ctx: Context<SettleAuction>, alias: String) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_REGISTRATIONS)?;
+        let auction = &ctx.accounts.auction;
+        require!(Clock::get()?.unix_timestamp >= auction.end_ts, UnikError::AuctionNotEnded);
+
+        let winner = auction.highest_bidder;
+        let winning_bid = auction.highest_bid;
+        if winner != Pubkey::default() {
+            let treasury = ctx.accounts.treasury.as_ref().ok_or(UnikError::InvalidTreasury)?;
+            let config = load_config(&ctx.accounts.config)?;
+            require!(config.map(|config| config.treasury) == Some(treasury.key()), UnikError::InvalidTreasury);
+            auction.sub_lamports(winning_bid)?;
+            treasury.add_lamports(winning_bid)?;
+
+            let alias_account = ctx.accounts.alias_account.as_mut().ok_or(UnikError::InvalidAliasAccount)?;
+            let bump = ctx.bumps.alias_account.ok_or(UnikError::InvalidAliasAccount)?;
+            init_alias_account(alias_account, winner, alias.clone(), String::new(), bump)?;
+        } else {
+            // It would be created ownerless
+            require!(ctx.accounts.alias_account.is_none(), UnikError::InvalidAliasAccount);
+        }
+        // The Auction account returns its rent to the creator (`close = creator`)
+
+        msg!("Auction settled for alias: {}", alias);
+        emit!(AuctionSettledEvent {
+            alias,
+            winner: (winner != Pubkey::default()).then_some(winner),
+            amount: winning_bid,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Call off an auction that has no bids (config authority only)
+    pub fn cancel_auction(ctx: Context<CancelAuction>, alias: String) -> Result<()> {
+        require!(ctx.accounts.auction.highest_bidder == Pubkey::default(), UnikError::AuctionHasBids);
+
+        msg!("Auction cancelled for alias: {}", alias);
+        emit!(AuctionSettledEvent {
+            alias,
+            winner: None,
+            amount: 0,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Rewrite a program account stored in an older layout in the current one, preserving its data.
+    /// Permissionless since nothing but the layout changes; `payer` funds any extra rent.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let info = ctx.accounts.account.to_account_info();
+        require!(info.owner == &crate::ID && info.data_len() >= 8, UnikError::InvalidPDA);
+        let discriminator: [u8; 8] = info.try_borrow_data()?[..8].try_into().unwrap();
+
+        let payer = ctx.accounts.payer.to_account_info();
+        let system_program = ctx.accounts.system_program.to_account_info();
+        let schema_version = match discriminator {
+            AliasAccount::DISCRIMINATOR => migrate_layout::<AliasAccount>(&info, &payer, &system_program)?,
+            RouteAccount::DISCRIMINATOR => migrate_layout::<RouteAccount>(&info, &payer, &system_program)?,
+            PaymentLimits::DISCRIMINATOR => migrate_layout::<PaymentLimits>(&info, &payer, &system_program)?,
+            ReferralPolicy::DISCRIMINATOR => migrate_layout::<ReferralPolicy>(&info, &payer, &system_program)?,
+            FiatPriceFeed::DISCRIMINATOR => migrate_layout::<FiatPriceFeed>(&info, &payer, &system_program)?,
+            PaymentStats::DISCRIMINATOR => migrate_layout::<PaymentStats>(&info, &payer, &system_program)?,
+            Receipt::DISCRIMINATOR => migrate_layout::<Receipt>(&info, &payer, &system_program)?,
+            Escrow::DISCRIMINATOR => migrate_layout::<Escrow>(&info, &payer, &system_program)?,
+            Subscription::DISCRIMINATOR => migrate_layout::<Subscription>(&info, &payer, &system_program)?,
+            Stream::DISCRIMINATOR => migrate_layout::<Stream>(&info, &payer, &system_program)?,
+            Config::DISCRIMINATOR => migrate_layout::<Config>(&info, &payer, &system_program)?,
+            MintFilter::DISCRIMINATOR => migrate_layout::<MintFilter>(&info, &payer, &system_program)?,
+            Listing::DISCRIMINATOR => migrate_layout::<Listing>(&info, &payer, &system_program)?,
+            Offer::DISCRIMINATOR => migrate_layout::<Offer>(&info, &payer, &system_program)?,
+            Auction::DISCRIMINATOR => migrate_layout::<Auction>(&info, &payer, &system_program)?,
+            _ => return err!(UnikError::InvalidPDA),
+        };
+
+        msg!("Account {} migrated to schema version {}", info.key(), schema_version);
+        emit!(AccountMigratedEvent {
+            account: info.key(),
+            schema_version,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Migrate a stale route account that can't be deserialized with the current schema.
+    /// Uses UncheckedAccount to bypass Anchor deserialization.
+    /// Prefer `migrate_account`, which keeps the route; this is for layouts it can't decode.
+    /// Only the alias owner can call this. Closes the old account and refunds rent.
+    pub fn migrate_route_account(ctx: Context<MigrateRouteAccount>, alias: String) -> Result<()> {
+        let alias_account = &ctx.accounts.alias_account;
+        require!(alias_account.owner == ctx.accounts.user.key(), UnikError::Unauthorized);
+
+        // Validate the route_account PDA manually
+        let (expected_route_pda, _bump) = Pubkey::find_program_address(
+            &[b"route", alias.as_bytes()],
+            ctx.program_id,
         );
         require!(
             ctx.accounts.route_account.key() == expected_route_pda,
@@ -296,36 +2062,25 @@ pub mod unik_anchor {
             UnikError::InvalidPDA
         );
 
-        // Close the account: transfer all lamports to user, zero data, assign to system program
-        let route_info = ctx.accounts.route_account.to_account_info();
-        let user_info = ctx.accounts.user.to_account_info();
-
-        let route_lamports = route_info.lamports();
-        **route_info.try_borrow_mut_lamports()? = 0;
-        **user_info.try_borrow_mut_lamports()? = user_info.lamports()
-            .checked_add(route_lamports)
-            .ok_or(UnikError::Overflow)?;
-
-        // Zero account data
-        route_info.try_borrow_mut_data()?.fill(0);
-        route_info.assign(&anchor_lang::solana_program::system_program::ID);
+        let previous_splits_hash = stored_splits_hash(&ctx.accounts.route_account);
+        close_program_account(&ctx.accounts.route_account, &ctx.accounts.user)?;
 
         msg!("Stale route account migrated (closed) for alias: {}", alias);
-        emit!(RouteEvent {
+        emit!(RouteMigratedEvent {
             alias: alias.clone(),
-            splits_count: 0,
+            previous_splits_hash,
             timestamp: Clock::get()?.unix_timestamp,
         });
         Ok(())
     }
 
     /// Delete a route config independently - refunds rent to owner
-    /// Must be called BEFORE delete_alias if a route exists.
-    pub fn delete_route_config(_ctx: Context<DeleteRouteConfig>, alias: String) -> Result<()> {
+    /// (delete_alias also closes the route, so this is only needed to keep the alias).
+    pub fn delete_route_config(ctx: Context<DeleteRouteConfig>, alias: String) -> Result<()> {
         msg!("Route config deleted for alias: {}", alias);
-        emit!(RouteEvent {
+        emit!(RouteDeletedEvent {
             alias: alias.clone(),
-            splits_count: 0,
+            previous_splits_hash: ctx.accounts.route_account.splits_hash()?,
             timestamp: Clock::get()?.unix_timestamp,
         });
         Ok(())
@@ -333,251 +2088,3234 @@ pub mod unik_anchor {
 
     /// Delete an alias permanently - refunds rent to owner
     /// The alias becomes available for registration by anyone
-    /// NOTE: If a route config exists, call delete_route_config FIRST.
-    pub fn delete_alias(_ctx: Context<DeleteAlias>, alias: String) -> Result<()> {
-        // Validation is completely handled by Anchor's `seeds`, `bump` and `constraint` macros.
-        // It automatically closes the account and refunds rent to `close = user`.
-        msg!("Alias deleted: {}", alias);
-        emit!(AliasEvent {
-            event_type: "DELETE".to_string(),
+    /// The route, referral policy, mint filter and listing are closed with it; pass the alias's PaymentLimits, PaymentStats
+    /// and FiatPriceFeed accounts (writable) in remaining_accounts to close those as well.
+    /// Open escrows, streams, subscriptions and offers block deletion; the owner can end each of them
+    /// (refund_escrow, cancel_stream, cancel_subscription, cancel_offer).
+    pub fn delete_alias<'info>(ctx: Context<'_, '_, '_, 'info, DeleteAlias<'info>>, alias: String) -> Result<()> {
+        // The alias account itself is closed by Anchor (`close = user`).
+        // Dependents at fixed PDAs are closed here so a re-registered alias can't inherit them.
+        // Deleting would let the owner escape a pending compliance review
+        require!(ctx.accounts.alias_account.freeze_reason == 0, UnikError::AliasFrozen);
+        // The NFT would outlive the alias; detokenize_alias first
+        require!(ctx.accounts.alias_account.nft_mint == Pubkey::default(), UnikError::AliasTokenized);
+        // A re-registration reuses the alias PDA, so escrows, streams, subscriptions and offers made to
+        // this alias would otherwise pay into (or be settled by) whoever registers it next
+        require!(ctx.accounts.alias_account.open_obligations == 0, UnikError::AliasHasOpenObligations);
+
+        let user_info = ctx.accounts.user.to_account_info();
+        let route_info = &ctx.accounts.route_account;
+        if route_info.owner == &crate::ID && !route_info.data_is_empty() {
+            if let Some(previous_splits_hash) = stored_splits_hash(route_info) {
+                emit!(RouteDeletedEvent {
+                    alias: alias.clone(),
+                    previous_splits_hash,
+                    timestamp: Clock::get()?.unix_timestamp,
+                });
+            }
+        }
+        for dependent in [
+            &ctx.accounts.route_account,
+            &ctx.accounts.referral_policy,
+            &ctx.accounts.mint_filter,
+        ] {
+            if dependent.owner == &crate::ID && !dependent.data_is_empty() {
+                close_program_account(dependent, &user_info)?;
+            }
+        }
+        // A listing's rent belongs to its seller, who may be a previous owner
+        if let Some(listing) = load_optional::<Listing>(&ctx.accounts.listing)? {
+            let seller_info = if listing.seller == ctx.accounts.user.key() {
+                user_info.clone()
+            } else {
+                let seller = ctx.accounts.listing_seller.as_ref().ok_or(UnikError::InvalidRecipientAccount)?;
+                require!(seller.key() == listing.seller, UnikError::InvalidRecipientAccount);
+                seller.to_account_info()
+            };
+            close_program_account(&ctx.accounts.listing, &seller_info)?;
+        }
+
+        // Per-mint / per-currency PDAs can't be derived without their key, so the client passes them
+        let alias_key = ctx.accounts.alias_account.key();
+        for dependent in ctx.remaining_accounts {
+            require!(dependent.is_writable && dependent.owner == &crate::ID, UnikError::InvalidPDA);
+            {
+                let data = dependent.try_borrow_data()?;
+                require!(data.len() >= 40, UnikError::InvalidPDA);
+                let discriminator = &data[..8];
+                require!(
+                    discriminator == PaymentLimits::DISCRIMINATOR
+                        || discriminator == PaymentStats::DISCRIMINATOR
+                        || discriminator == FiatPriceFeed::DISCRIMINATOR,
+                    UnikError::InvalidPDA
+                );
+                // All three start with alias_ref
+                require!(data[8..40] == alias_key.to_bytes(), UnikError::InvalidPDA);
+            }
+            close_program_account(dependent, &user_info)?;
+        }
+
+        msg!("Alias deleted: {} ({} per-mint accounts closed)", alias, ctx.remaining_accounts.len());
+        emit!(AliasDeletedEvent {
             alias: alias.clone(),
-            owner: _ctx.accounts.user.key(),
+            owner: ctx.accounts.user.key(),
+            metadata_uri: ctx.accounts.alias_account.metadata_uri.clone(),
+            version: ctx.accounts.alias_account.version,
             timestamp: Clock::get()?.unix_timestamp,
         });
         Ok(())
     }
 }
 
-#[derive(Accounts)]
-#[instruction(alias: String)]
-pub struct RegisterAlias<'info> {
-    #[account(
-        init,
-        payer = user,
-        // 8 (discriminator) + 32 (owner) + 4+32 (alias string) + 4+200 (metadata_uri) + 8 (version) + 1 (is_active) + 8 (registered_at) + 1 (bump)
-        space = 8 + 32 + 4 + 32 + 4 + 200 + 8 + 1 + 8 + 1,
-        seeds = [b"alias", alias.as_bytes()],
-        bump
-    )]
-    pub alias_account: Account<'info, AliasAccount>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
+/// Protocol default minimum payment when the owner has not set limits:
+/// 0.00001 of a whole token (10000 lamports for SOL), and never less than 1 base unit.
+pub fn default_min_amount(decimals: u8) -> u64 {
+    10u64.checked_pow(decimals.saturating_sub(5) as u32).unwrap_or(u64::MAX)
 }
 
-#[derive(Accounts)]
-#[instruction(alias: String)]
-pub struct UpdateAlias<'info> {
-    #[account(
-        mut,
-        seeds = [b"alias", alias.as_bytes()],
-        bump,
-        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
-    )]
-    pub alias_account: Account<'info, AliasAccount>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
+/// Enforce the owner-configured limits for a mint, falling back to the protocol default minimum
+fn require_within_limits(payment_limits: &AccountInfo, amount: u64, decimals: u8) -> Result<()> {
+    match load_optional::<PaymentLimits>(payment_limits)? {
+        Some(limits) => {
+            require!(amount >= limits.min_amount, UnikError::AmountTooSmall);
+            if let Some(max) = limits.max_amount {
+                require!(amount <= max, UnikError::AmountTooLarge);
+            }
+        }
+        None => require!(amount >= default_min_amount(decimals), UnikError::AmountTooSmall),
+    }
+    Ok(())
 }
 
-#[derive(Accounts)]
-#[instruction(alias: String)]
-pub struct DeleteAlias<'info> {
-    #[account(
-        mut,
-        seeds = [b"alias", alias.as_bytes()],
-        bump,
-        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
-        close = user,  // Refund rent to user
-    )]
-    pub alias_account: Account<'info, AliasAccount>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
+/// CRIT-01: Payments need an active alias that isn't under a compliance freeze.
+/// Backwards compatibility: legacy aliases have registered_at = 0, so treat them as active
+fn require_accepting_payments(alias_account: &AliasAccount) -> Result<()> {
+    require!(alias_account.is_active || alias_account.registered_at == 0, UnikError::AliasInactive);
+    require!(alias_account.freeze_reason == 0, UnikError::AliasFrozen);
+    Ok(())
 }
 
-#[derive(Accounts)]
-#[instruction(alias: String)]
-pub struct MigrateRouteAccount<'info> {
-    /// The alias account - validated normally since its schema hasn't changed
-    #[account(
+/// Fail if the alias has a mint filter that rejects `mint`
+fn require_mint_accepted(mint_filter: &AccountInfo, mint: &Pubkey) -> Result<()> {
+    if let Some(mint_filter) = load_optional::<MintFilter>(mint_filter)? {
+        require!(mint_filter.accepts(mint), UnikError::MintNotAccepted);
+    }
+    Ok(())
+}
+
+fn require_valid_alias(alias: &str) -> Result<()> {
+    require!(alias.len() >= 3 && alias.len() <= 32, UnikError::InvalidAliasLength);
+    require!(alias.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'), UnikError::InvalidAliasCharacters);
+    Ok(())
+}
+
+/// Fill a freshly created alias account for `owner` - shared by register_alias and settle_auction
+fn init_alias_account(alias_account: &mut AliasAccount, owner: Pubkey, alias: String, metadata_uri: String, bump: u8) -> Result<()> {
+    alias_account.owner = owner;
+    alias_account.alias = alias;
+    alias_account.metadata_uri = metadata_uri;
+    alias_account.version = 1;  // Initial version
+    alias_account.is_active = true;  // Active by default
+    alias_account.registered_at = Clock::get()?.unix_timestamp;
+    alias_account.bump = bump;
+    alias_account.schema_version = AliasAccount::SCHEMA_VERSION;
+
+    msg!("Alias registered: {} (version 1)", alias_account.alias);
+    emit!(AliasRegisteredEvent {
+        alias: alias_account.alias.clone(),
+        owner: alias_account.owner,
+        metadata_uri: alias_account.metadata_uri.clone(),
+        version: alias_account.version,
+        timestamp: alias_account.registered_at,
+    });
+    Ok(())
+}
+
+/// Fail unless the alias can change hands through the marketplace
+fn require_transferable(alias_account: &AliasAccount) -> Result<()> {
+    require!(alias_account.freeze_reason == 0, UnikError::AliasFrozen);
+    // The NFT holder is the owner; a direct sale would leave the NFT pointing at the wrong wallet
+    require!(alias_account.nft_mint == Pubkey::default(), UnikError::AliasTokenized);
+    Ok(())
+}
+
+/// Hand a sold alias to `buyer`: bump `version` and point an existing route entirely at the
+/// buyer, so payments stop reaching splits the seller configured.
+fn transfer_alias(alias_account: &mut AliasAccount, route_info: &AccountInfo, buyer: Pubkey) -> Result<()> {
+    let old_owner = alias_account.owner;
+    alias_account.owner = buyer;
+    alias_account.version = alias_account.version.checked_add(1).unwrap_or(alias_account.version);
+    let now = Clock::get()?.unix_timestamp;
+
+    if let Some(mut route) = load_optional::<RouteAccount>(route_info)? {
+        let previous_splits_hash = route.splits_hash()?;
+        route.splits = vec![Split { recipient: buyer, percentage: 10000 }];
+        route.consented_mask = 0;
+        route.sol_delivery = Vec::new();
+        // One split always fits in the space the route was created with
+        let mut data = route_info.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = &mut data[..];
+        route.try_serialize(&mut writer)?;
+
+        emit!(RouteSetEvent {
+            alias: alias_account.alias.clone(),
+            splits: route.splits,
+            previous_splits_hash,
+            timestamp: now,
+        });
+    }
+
+    emit!(AliasTransferredEvent {
+        alias: alias_account.alias.clone(),
+        old_owner,
+        new_owner: buyer,
+        version: alias_account.version,
+        timestamp: now,
+    });
+    Ok(())
+}
+
+fn offer_vault<'a, 'info>(
+    vault: &'a Option<Account<'info, TokenAccount>>,
+    token_program: &'a Option<Program<'info, Token>>,
+) -> Result<(&'a Account<'info, TokenAccount>, &'a Program<'info, Token>)> {
+    Ok((
+        vault.as_ref().ok_or(UnikError::MissingEscrowVault)?,
+        token_program.as_ref().ok_or(UnikError::MissingEscrowVault)?,
+    ))
+}
+
+fn close_offer_vault<'info>(
+    offer: &Account<'info, Offer>,
+    vault: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    buyer: &AccountInfo<'info>,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    let cpi_accounts = token::CloseAccount {
+        account: vault.to_account_info(),
+        destination: buyer.clone(),
+        authority: offer.to_account_info(),
+    };
+    token::close_account(CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, &[signer_seeds]))
+}
+
+/// Read the Config if it exists, in any of its layouts: fields added since it was last migrated read as zero
+fn load_config(config: &AccountInfo) -> Result<Option<Config>> {
+    if config.owner != &crate::ID || config.data_is_empty() {
+        return Ok(None);
+    }
+    let mut data = config.try_borrow_data()?.to_vec();
+    data.resize(data.len().max(Config::SPACE), 0);
+    Ok(Some(Config::try_deserialize(&mut &data[..])?))
+}
+
+/// Count an escrow, stream, subscription or offer held against the alias; it can't be deleted until they settle
+fn add_obligation(alias_account: &mut AliasAccount) -> Result<()> {
+    alias_account.open_obligations = alias_account.open_obligations.checked_add(1).ok_or(UnikError::Overflow)?;
+    Ok(())
+}
+
+/// Undo `add_obligation` once the escrow, stream, subscription or offer is closed.
+/// The alias may be gone (obligations made before the count existed), so it's only updated if it exists.
+fn release_obligation(alias_account: &AccountInfo) -> Result<()> {
+    if let Some(mut alias) = load_alias(alias_account)? {
+        if alias.open_obligations > 0 {
+            alias.open_obligations -= 1;
+            let mut data = alias_account.try_borrow_mut_data()?;
+            alias.try_serialize(&mut &mut data[..])?;
+        }
+    }
+    Ok(())
+}
+
+/// Read the AliasAccount if it exists, in any of its layouts (see `load_config`)
+fn load_alias(alias_account: &AccountInfo) -> Result<Option<AliasAccount>> {
+    if alias_account.owner != &crate::ID || alias_account.data_is_empty() {
+        return Ok(None);
+    }
+    let mut data = alias_account.try_borrow_data()?.to_vec();
+    data.resize(data.len().max(AliasAccount::SPACE), 0);
+    Ok(Some(AliasAccount::try_deserialize(&mut &data[..])?))
+}
+
+/// Fail if the protocol config exists and has any of `flags` paused
+fn require_not_paused(config: &AccountInfo, flags: u8) -> Result<()> {
+    if let Some(config) = load_config(config)? {
+        require!(config.paused_flags & flags == 0, UnikError::ProtocolPaused);
+    }
+    Ok(())
+}
+
+fn require_memo_len(memo: &Option<String>) -> Result<()> {
+    if let Some(memo) = memo {
+        require!(memo.len() <= MAX_MEMO_LEN, UnikError::MemoTooLong);
+    }
+    Ok(())
+}
+
+/// Update the PaymentStats PDA for this payment if the owner has initialized it
+fn record_payment_stats(payment_stats: &AccountInfo, payer: &Pubkey, amount: u64) -> Result<()> {
+    if let Some(mut stats) = load_optional::<PaymentStats>(payment_stats)? {
+        stats.record(payer, amount, Clock::get()?.unix_timestamp)?;
+        let mut data = payment_stats.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = &mut data[..];
+        stats.try_serialize(&mut writer)?;
+    }
+    Ok(())
+}
+
+/// Aggregate price read from a Pyth price account: SOL = price * 10^expo units of the quote currency
+pub struct OraclePrice {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_time: i64,
+}
+
+/// Parse the aggregate price of a Pyth (v2) price account.
+/// Only the fields needed here are read, so tests can mock a feed with a plain byte buffer.
+pub fn read_pyth_price(data: &[u8]) -> Result<OraclePrice> {
+    const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+    const PYTH_VERSION: u32 = 2;
+    const ACCOUNT_TYPE_PRICE: u32 = 3;
+    const STATUS_TRADING: u32 = 1;
+
+    require!(data.len() >= 240, UnikError::InvalidPriceFeed);
+    let u32_at = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    let i64_at = |offset: usize| i64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+
+    require!(
+        u32_at(0) == PYTH_MAGIC && u32_at(4) == PYTH_VERSION && u32_at(8) == ACCOUNT_TYPE_PRICE,
+        UnikError::InvalidPriceFeed
+    );
+    require!(u32_at(224) == STATUS_TRADING, UnikError::InvalidPriceFeed);
+
+    let price = OraclePrice {
+        expo: u32_at(20) as i32,
+        publish_time: i64_at(96),
+        price: i64_at(208),
+        conf: i64_at(216) as u64,
+    };
+    require!(price.price > 0, UnikError::InvalidPriceFeed);
+    // Keeps `fiat_to_lamports` within u128 for any u64 amount
+    require!(price.expo.unsigned_abs() <= MAX_PRICE_EXPONENT, UnikError::InvalidPriceFeed);
+    Ok(price)
+}
+
+/// Reject a price older than the feed's max staleness or less certain than its max confidence
+fn require_usable_price(price: &OraclePrice, fiat_feed: &FiatPriceFeed, now: i64) -> Result<()> {
+    require!(now.saturating_sub(price.publish_time) <= fiat_feed.max_staleness, UnikError::StalePrice);
+    require!(
+        (price.conf as u128) * 10000 <= (fiat_feed.max_confidence_bps as u128) * (price.price as u128),
+        UnikError::PriceConfidenceTooWide
+    );
+    Ok(())
+}
+
+/// lamports = fiat_amount * 10^-FIAT_DECIMALS / (price * 10^expo) * 10^SOL_DECIMALS
+pub fn fiat_to_lamports(fiat_amount: u64, price: &OraclePrice) -> Result<u64> {
+    let exponent = SOL_DECIMALS as i64 - FIAT_DECIMALS as i64 - price.expo as i64;
+    let scale = 10u128.checked_pow(exponent.unsigned_abs() as u32).ok_or(UnikError::Overflow)?;
+    let lamports = if exponent >= 0 {
+        (fiat_amount as u128)
+            .checked_mul(scale)
+            .ok_or(UnikError::Overflow)?
+            .checked_div(price.price as u128)
+    } else {
+        (fiat_amount as u128).checked_div(scale.checked_mul(price.price as u128).ok_or(UnikError::Overflow)?)
+    }
+    .ok_or(UnikError::Overflow)?;
+    Ok(u64::try_from(lamports).map_err(|_| UnikError::Overflow)?)
+}
+
+/// Reject the payment if the route's splits no longer match what the payer was shown
+fn require_route_hash(route: &RouteAccount, expected_route_hash: Option<[u8; 32]>) -> Result<()> {
+    if let Some(expected) = expected_route_hash {
+        require!(route.splits_hash()? == expected, UnikError::RouteChanged);
+    }
+    Ok(())
+}
+
+/// Validate a payer-requested referral against the alias's policy and compute the referrer's share.
+/// Returns None when no referral is requested (`referral_bps == 0`).
+fn referral_share(
+    policy: Option<&ReferralPolicy>,
+    referrer: Option<Pubkey>,
+    payer: &Pubkey,
+    amount: u64,
+    referral_bps: u16,
+) -> Result<Option<(Pubkey, u64)>> {
+    if referral_bps == 0 {
+        return Ok(None);
+    }
+    let policy = policy.ok_or(UnikError::ReferralsNotEnabled)?;
+    let referrer = referrer.ok_or(UnikError::MissingReferrer)?;
+
+    require!(referral_bps <= policy.max_bps, UnikError::ReferralFeeTooHigh);
+    // Payers cannot refer themselves to claw back part of the payment
+    require!(referrer != *payer, UnikError::ReferrerNotAllowed);
+    require!(policy.open || policy.allowed_referrers.contains(&referrer), UnikError::ReferrerNotAllowed);
+
+    let referral_amount = (amount as u128)
+        .checked_mul(referral_bps as u128)
+        .ok_or(UnikError::Overflow)?
+        / 10000;
+    Ok(Some((referrer, referral_amount as u64)))
+}
+
+/// Shared body of `execute_transfer` and `execute_transfer_fiat`
+#[allow(clippy::too_many_arguments)]
+fn process_sol_payment<'info>(
+    accounts: &ExecuteTransfer<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    alias: String,
+    amount: u64,
+    memo: Option<String>,
+    referral_bps: u16,
+    expected_route_hash: Option<[u8; 32]>,
+) -> Result<()> {
+    require_not_paused(&accounts.config, PAUSE_SOL_TRANSFERS)?;
+    require_within_limits(&accounts.payment_limits, amount, SOL_DECIMALS)?;
+    require_memo_len(&memo)?;
+    require_route_hash(&accounts.route_account, expected_route_hash)?;
+
+    require_accepting_payments(&accounts.alias_account)?;
+
+    let route = &accounts.route_account;
+    let splits = &route.splits;
+
+    msg!("Executing transfer of {} lamports for {} splits", amount, splits.len());
+
+    let user_info = accounts.user.to_account_info();
+    let system_program_info = accounts.system_program.to_account_info();
+
+    let referral = referral_share(
+        accounts.referral_policy.as_deref(),
+        accounts.referrer.as_ref().map(|referrer| referrer.key()),
+        &accounts.user.key(),
+        amount,
+        referral_bps,
+    )?;
+    let split_total = match &referral {
+        Some((_, referral_amount)) => {
+            let referrer_info = accounts.referrer.as_ref().ok_or(UnikError::MissingReferrer)?.to_account_info();
+            if *referral_amount > 0 {
+                require!(
+                    referrer_info.owner == &system_program::ID || referrer_info.lamports() > 0,
+                    UnikError::InvalidRecipientAccount
+                );
+                let cpi_context = CpiContext::new(
+                    system_program_info.clone(),
+                    system_program::Transfer {
+                        from: user_info.clone(),
+                        to: referrer_info,
+                    }
+                );
+                system_program::transfer(cpi_context, *referral_amount)?;
+            }
+            amount - referral_amount
+        }
+        None => amount,
+    };
+
+    let amounts = split_amounts(splits, split_total)?;
+
+    // Recipients preferring wrapped SOL are paid into their wSOL ATA
+    let destinations: Vec<Pubkey> = splits.iter().enumerate().map(|(i, split)| {
+        if route.sol_delivery_for(i) == SolDelivery::Wrapped {
+            get_associated_token_address(&split.recipient, &native_mint::ID)
+        } else {
+            split.recipient
+        }
+    }).collect();
+    // CRIT-01 explicit security lock
+    require_split_destinations(remaining_accounts, &destinations, UnikError::InvalidRecipientAccount)?;
+
+    for (i, (recipient_acc, split_amount)) in remaining_accounts.iter().zip(amounts).enumerate() {
+        let wrap = route.sol_delivery_for(i) == SolDelivery::Wrapped;
+
+        if split_amount > 0 {
+            require!(
+                recipient_acc.owner == &system_program::ID || recipient_acc.lamports() > 0,
+                UnikError::InvalidRecipientAccount
+            );
+
+            let cpi_context = CpiContext::new(
+                system_program_info.clone(),
+                system_program::Transfer {
+                    from: user_info.clone(),
+                    to: recipient_acc.clone(),
+                }
+            );
+            system_program::transfer(cpi_context, split_amount)?;
+
+            if wrap {
+                let token_program = accounts.token_program.as_ref().ok_or(UnikError::MissingTokenProgram)?;
+                token::sync_native(CpiContext::new(
+                    token_program.to_account_info(),
+                    token::SyncNative { account: recipient_acc.clone() },
+                ))?;
+            }
+        }
+    }
+
+    record_payment_stats(&accounts.payment_stats, &accounts.user.key(), amount)?;
+
+    if let Some(receipt) = &accounts.receipt {
+        let reference = accounts.reference.as_ref().ok_or(UnikError::MissingReference)?;
+        create_receipt(
+            receipt,
+            &user_info,
+            &system_program_info,
+            Receipt {
+                alias_ref: accounts.alias_account.key(),
+                payer: accounts.user.key(),
+                mint: NATIVE_SOL_MINT,
+                // Refundable amount: the referral share is not held by the route recipients
+                amount: split_total,
+                splits: splits.clone(),
+                refunded_amount: 0,
+                is_refunded: false,
+                reference: reference.key(),
+                created_at: Clock::get()?.unix_timestamp,
+                bump: 0,
+                schema_version: Receipt::SCHEMA_VERSION,
+            },
+        )?;
+    }
+
+    if let Some((referrer, referral_amount)) = referral {
+        emit!(ReferralPaidEvent {
+            alias: alias.clone(),
+            payer: accounts.user.key(),
+            referrer,
+            mint: NATIVE_SOL_MINT,
+            amount: referral_amount,
+            referral_bps,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+    }
+
+    emit!(PaymentEvent {
+        alias,
+        payer: accounts.user.key(),
+        mint: NATIVE_SOL_MINT,
+        amount,
+        reference: accounts.reference.as_ref().map(|r| r.key()),
+        memo,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+/// Transfer `amount` of `mint` from `source` to the route recipients, split by basis points.
+/// `recipient_atas` must hold each recipient's ATA in split order.
+#[allow(clippy::too_many_arguments)]
+fn distribute_tokens<'info>(
+    splits: &[Split],
+    amount: u64,
+    mint: &Pubkey,
+    source: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    recipient_atas: &[AccountInfo<'info>],
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let expected_atas: Vec<Pubkey> = splits.iter()
+        .map(|split| get_associated_token_address(&split.recipient, mint))
+        .collect();
+    require_split_destinations(recipient_atas, &expected_atas, UnikError::InvalidRecipientAta)?;
+
+    let amounts = split_amounts(splits, amount)?;
+
+    for ((split, split_amount), recipient_ata) in splits.iter().zip(amounts).zip(recipient_atas) {
+        if split_amount > 0 {
+            msg!("Sending {} tokens to recipient {} (ATA: {})", split_amount, split.recipient, recipient_ata.key());
+
+            let cpi_accounts = Transfer {
+                from: source.clone(),
+                to: recipient_ata.clone(),
+                authority: authority.clone(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer_seeds);
+            token::transfer(cpi_ctx, split_amount)?;
+        }
+    }
+    Ok(())
+}
+
+/// wSOL variant of `distribute_tokens` for routes where some recipients prefer native SOL.
+/// Their shares are unwrapped through a temporary token account at [b"unwrap", user] and sent as lamports.
+/// `recipients[i]` is the recipient wallet for unwrapped legs and the wSOL ATA otherwise.
+fn distribute_wsol_unwrapping<'info>(
+    accounts: &ExecuteTokenTransfer<'info>,
+    splits: &[Split],
+    unwrap_legs: &[bool],
+    amount: u64,
+    recipients: &[AccountInfo<'info>],
+) -> Result<()> {
+    let destinations: Vec<Pubkey> = splits.iter().zip(unwrap_legs).map(|(split, unwrap)| {
+        if *unwrap {
+            split.recipient
+        } else {
+            get_associated_token_address(&split.recipient, &native_mint::ID)
+        }
+    }).collect();
+    require_split_destinations(recipients, &destinations, UnikError::InvalidRecipientAccount)?;
+
+    let user_info = accounts.user.to_account_info();
+    let user_token_info = accounts.user_token_account.to_account_info();
+    let token_program_info = accounts.token_program.to_account_info();
+    let amounts = split_amounts(splits, amount)?;
+
+    let mut native_total: u64 = 0;
+    for (i, split_amount) in amounts.iter().enumerate() {
+        let destination = &recipients[i];
+        if unwrap_legs[i] {
+            native_total = native_total.checked_add(*split_amount).ok_or(UnikError::Overflow)?;
+        } else if *split_amount > 0 {
+            let cpi_accounts = Transfer {
+                from: user_token_info.clone(),
+                to: destination.clone(),
+                authority: user_info.clone(),
+            };
+            token::transfer(CpiContext::new(token_program_info.clone(), cpi_accounts), *split_amount)?;
+        }
+    }
+
+    if native_total == 0 {
+        return Ok(());
+    }
+
+    // Move the native legs' wSOL into a temporary account and close it back to the payer,
+    // which turns it into lamports the payer then forwards to each recipient
+    let unwrap_account = accounts.unwrap_account.as_ref().ok_or(UnikError::MissingUnwrapAccount)?.to_account_info();
+    let user_key = accounts.user.key();
+    let (expected_unwrap, bump) = Pubkey::find_program_address(&[b"unwrap", user_key.as_ref()], &crate::ID);
+    require!(unwrap_account.key() == expected_unwrap, UnikError::InvalidPDA);
+    let signer_seeds: &[&[u8]] = &[b"unwrap", user_key.as_ref(), &[bump]];
+
+    create_pda_account(
+        &user_info,
+        &unwrap_account,
+        &accounts.system_program.to_account_info(),
+        TokenAccount::LEN,
+        &token::ID,
+        signer_seeds,
+    )?;
+    token::initialize_account3(CpiContext::new(
+        token_program_info.clone(),
+        token::InitializeAccount3 {
+            account: unwrap_account.clone(),
+            mint: accounts.mint.to_account_info(),
+            authority: unwrap_account.clone(),
+        },
+    ))?;
+    token::transfer(
+        CpiContext::new(
+            token_program_info.clone(),
+            Transfer {
+                from: user_token_info,
+                to: unwrap_account.clone(),
+                authority: user_info.clone(),
+            },
+        ),
+        native_total,
+    )?;
+    token::close_account(CpiContext::new_with_signer(
+        token_program_info,
+        token::CloseAccount {
+            account: unwrap_account.clone(),
+            destination: user_info.clone(),
+            authority: unwrap_account,
+        },
+        &[signer_seeds],
+    ))?;
+
+    for (i, split_amount) in amounts.into_iter().enumerate() {
+        if unwrap_legs[i] && split_amount > 0 {
+            let cpi_context = CpiContext::new(
+                accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: user_info.clone(),
+                    to: recipients[i].clone(),
+                }
+            );
+            system_program::transfer(cpi_context, split_amount)?;
+        }
+    }
+    Ok(())
+}
+
+/// The alias an escrow was made to, or None once it has been deleted. A re-registration reuses
+/// the PDA, so an alias registered after the escrow was created counts as deleted too.
+fn escrow_alias(alias_account: &AccountInfo, escrow: &Escrow) -> Result<Option<AliasAccount>> {
+    let Some(alias) = load_alias(alias_account)? else {
+        return Ok(None);
+    };
+    Ok((alias.registered_at <= escrow.created_at).then_some(alias))
+}
+
+/// Move escrowed funds into the route (`release`) or back to the payer, then close the vault.
+/// The Escrow account itself is closed to the payer by the `SettleEscrow` constraints.
+fn settle_escrow<'info>(ctx: Context<'_, '_, '_, 'info, SettleEscrow<'info>>, alias: &str, release: bool) -> Result<()> {
+    let escrow = &ctx.accounts.escrow;
+    let amount = escrow.amount;
+    let is_sol = escrow.mint == NATIVE_SOL_MINT;
+    // Refunds stay available while payments are paused so payers can always exit
+    if release {
+        require_not_paused(&ctx.accounts.config, if is_sol { PAUSE_SOL_TRANSFERS } else { PAUSE_TOKEN_TRANSFERS })?;
+        let alias_account = escrow_alias(&ctx.accounts.alias_account, escrow)?.ok_or(UnikError::AliasGone)?;
+        require!(alias_account.freeze_reason == 0, UnikError::AliasFrozen);
+    }
+    let escrow_id = escrow.escrow_id.to_le_bytes();
+    let signer_seeds: &[&[u8]] = &[b"escrow", alias.as_bytes(), escrow.payer.as_ref(), &escrow_id, &[escrow.bump]];
+
+    let vault = if is_sol {
+        None
+    } else {
+        Some((
+            ctx.accounts.vault.as_ref().ok_or(UnikError::MissingEscrowVault)?,
+            ctx.accounts.token_program.as_ref().ok_or(UnikError::MissingEscrowVault)?,
+        ))
+    };
+
+    if release {
+        let route = ctx.accounts.route_account.as_ref().ok_or(UnikError::InvalidPDA)?;
+        require!(!route.splits.is_empty(), UnikError::RouteNotConfigured);
+        let remaining_accounts = ctx.remaining_accounts;
+
+        match vault {
+            None => {
+                let recipients: Vec<Pubkey> = route.splits.iter().map(|split| split.recipient).collect();
+                require_split_destinations(remaining_accounts, &recipients, UnikError::InvalidRecipientAccount)?;
+                let amounts = split_amounts(&route.splits, amount)?;
+                for (destination, share) in remaining_accounts.iter().zip(amounts) {
+                    escrow.sub_lamports(share)?;
+                    destination.add_lamports(share)?;
+                }
+            }
+            Some((vault, token_program)) => {
+                distribute_tokens(
+                    &route.splits,
+                    amount,
+                    &escrow.mint,
+                    &vault.to_account_info(),
+                    &escrow.to_account_info(),
+                    &token_program.to_account_info(),
+                    remaining_accounts,
+                    &[signer_seeds],
+                )?;
+            }
+        }
+
+        record_payment_stats(&ctx.accounts.payment_stats, &escrow.payer, amount)?;
+    } else {
+        match vault {
+            None => {
+                escrow.sub_lamports(amount)?;
+                ctx.accounts.payer.add_lamports(amount)?;
+            }
+            Some((vault, token_program)) => {
+                let payer_token_account = ctx.accounts.payer_token_account.as_ref().ok_or(UnikError::InvalidUserTokenAccount)?;
+                let cpi_accounts = Transfer {
+                    from: vault.to_account_info(),
+                    to: payer_token_account.to_account_info(),
+                    authority: escrow.to_account_info(),
+                };
+                token::transfer(
+                    CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, &[signer_seeds]),
+                    amount,
+                )?;
+            }
+        }
+    }
+
+    if let Some((vault, token_program)) = vault {
+        let cpi_accounts = token::CloseAccount {
+            account: vault.to_account_info(),
+            destination: ctx.accounts.payer.to_account_info(),
+            authority: escrow.to_account_info(),
+        };
+        token::close_account(CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, &[signer_seeds]))?;
+    }
+
+    if escrow_alias(&ctx.accounts.alias_account, escrow)?.is_some() {
+        release_obligation(&ctx.accounts.alias_account)?;
+    }
+
+    msg!("Escrow {} for alias: {}", if release { "released" } else { "refunded" }, alias);
+    emit!(EscrowSettledEvent {
+        escrow: escrow.key(),
+        alias: alias.to_string(),
+        payer: escrow.payer,
+        mint: escrow.mint,
+        amount,
+        released: release,
+        settled_by: ctx.accounts.authority.key(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+/// Create a PDA owned by `owner` with `space` bytes. Funds only the rent shortfall and then
+/// allocates and assigns, so lamports sent to the address beforehand can't block the creation.
+fn create_pda_account<'info>(
+    payer: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    system_program_info: &AccountInfo<'info>,
+    space: usize,
+    owner: &Pubkey,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    let shortfall = Rent::get()?.minimum_balance(space).saturating_sub(account.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program_info.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program_info.clone(),
+            system_program::Allocate { account_to_allocate: account.clone() },
+            &[signer_seeds],
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program_info.clone(),
+            system_program::Assign { account_to_assign: account.clone() },
+            &[signer_seeds],
+        ),
+        owner,
+    )
+}
+
+/// Create the Receipt PDA at [b"receipt", reference] for a payment that was just executed
+fn create_receipt<'info>(
+    receipt: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program_info: &AccountInfo<'info>,
+    mut data: Receipt,
+) -> Result<()> {
+    let (expected_receipt, bump) = Pubkey::find_program_address(&[b"receipt", data.reference.as_ref()], &crate::ID);
+    require!(receipt.key() == expected_receipt, UnikError::InvalidPDA);
+    // References are single use - a receipt can only be created once. Lamports alone don't count:
+    // anyone can pre-fund the address to squat on the reference.
+    require!(receipt.owner != &crate::ID || receipt.data_is_empty(), UnikError::ReceiptAlreadyExists);
+
+    let signer_seeds: &[&[u8]] = &[b"receipt", data.reference.as_ref(), &[bump]];
+    create_pda_account(payer, receipt, system_program_info, Receipt::SPACE, &crate::ID, signer_seeds)?;
+
+    data.bump = bump;
+    let mut account_data = receipt.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut account_data[..];
+    data.try_serialize(&mut writer)?;
+    Ok(())
+}
+
+/// Validate the canonical payment layout: `destinations[i]` is the writable account paid for split i,
+/// i.e. the recipient wallet for lamports or its token account (see `QuoteLeg::account`).
+/// Trailing extra accounts are ignored. Failures log the offending split index.
+fn require_split_destinations(destinations: &[AccountInfo], expected: &[Pubkey], mismatch: UnikError) -> Result<()> {
+    if destinations.len() < expected.len() {
+        msg!("Expected {} split destination accounts, got {}", expected.len(), destinations.len());
+        return err!(UnikError::MissingRecipient);
+    }
+    for (i, (destination, expected_key)) in destinations.iter().zip(expected).enumerate() {
+        if destination.key() != *expected_key {
+            msg!("Split {}: expected destination {}, got {}", i, expected_key, destination.key());
+            return Err(mismatch.into());
+        }
+        if !destination.is_writable {
+            msg!("Split {}: destination {} is not writable", i, expected_key);
+            return err!(UnikError::RecipientNotWritable);
+        }
+        if expected[..i].contains(expected_key) {
+            msg!("Split {}: destination {} is already paid by an earlier split", i, expected_key);
+            return err!(UnikError::DuplicateRecipientAccount);
+        }
+    }
+    Ok(())
+}
+
+/// Decode `data` (discriminator included) written in any historical layout of `T` into the current one
+/// by zero-padding it to the current size (see `VersionedAccount`).
+pub fn upgrade_layout<T: VersionedAccount>(data: &[u8]) -> Result<T> {
+    let mut padded = data.to_vec();
+    padded.resize(data.len().max(T::SPACE), 0);
+    let mut account = T::try_deserialize(&mut &padded[..])?;
+    require!(account.schema_version() < T::SCHEMA_VERSION, UnikError::AccountAlreadyMigrated);
+    account.set_schema_version(T::SCHEMA_VERSION);
+    Ok(account)
+}
+
+/// Rewrite `info` in the current layout of `T`, growing it to `T::SPACE` if needed.
+/// Returns the new schema version.
+fn migrate_layout<'info, T: VersionedAccount>(
+    info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<u8> {
+    let account = upgrade_layout::<T>(&info.try_borrow_data()?)?;
+    let mut new_data = Vec::new();
+    account.try_serialize(&mut new_data)?;
+
+    let new_len = info.data_len().max(T::SPACE);
+    let rent_due = Rent::get()?.minimum_balance(new_len).saturating_sub(info.lamports());
+    if rent_due > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: info.clone(),
+                },
+            ),
+            rent_due,
+        )?;
+    }
+    info.realloc(new_len, false)?;
+
+    let mut data = info.try_borrow_mut_data()?;
+    data.fill(0);
+    data[..new_data.len()].copy_from_slice(&new_data);
+    Ok(T::SCHEMA_VERSION)
+}
+
+/// Close a program-owned account outside Anchor's `close` constraint: move its lamports to
+/// `destination`, zero the data and hand it back to the system program.
+fn close_program_account(info: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    let lamports = info.lamports();
+    **info.try_borrow_mut_lamports()? = 0;
+    **destination.try_borrow_mut_lamports()? = destination.lamports()
+        .checked_add(lamports)
+        .ok_or(UnikError::Overflow)?;
+
+    info.try_borrow_mut_data()?.fill(0);
+    info.assign(&anchor_lang::solana_program::system_program::ID);
+    Ok(())
+}
+
+/// `RouteAccount::splits_hash` of a route account that may not decode with the current layout.
+/// Every route layout starts with alias_ref followed by the splits, so only that prefix is read.
+fn stored_splits_hash(info: &AccountInfo) -> Option<[u8; 32]> {
+    let data = info.try_borrow_data().ok()?;
+    let splits = Vec::<Split>::deserialize(&mut data.get(8 + 32..)?).ok()?;
+    Some(hash(&splits.try_to_vec().ok()?).to_bytes())
+}
+
+/// Check that an account passed in remaining_accounts sits at the program PDA for `seeds`
+fn require_pda(info: &AccountInfo, seeds: &[&[u8]]) -> Result<()> {
+    let (expected, _bump) = Pubkey::find_program_address(seeds, &crate::ID);
+    require!(info.key() == expected, UnikError::InvalidPDA);
+    Ok(())
+}
+
+/// Deserialize a required program account passed in remaining_accounts, validating its PDA and owner
+fn load_remaining_pda<T: AccountDeserialize>(info: &AccountInfo, seeds: &[&[u8]]) -> Result<T> {
+    require_pda(info, seeds)?;
+    require!(info.owner == &crate::ID, UnikError::InvalidPDA);
+    let data = info.try_borrow_data()?;
+    T::try_deserialize(&mut &data[..])
+}
+
+/// Deserialize an optional PDA that the caller must always pass (address enforced by seeds)
+/// but that only exists once its owner has initialized it. Returns None while uninitialized.
+fn load_optional<T: AccountDeserialize>(info: &AccountInfo) -> Result<Option<T>> {
+    if info.owner != &crate::ID || info.data_is_empty() {
+        return Ok(None);
+    }
+    let data = info.try_borrow_data()?;
+    Ok(Some(T::try_deserialize(&mut &data[..])?))
+}
+
+/// Split `amount` across `splits` by basis points.
+/// HIGH: Dust fix - the rounding remainder goes to the last recipient so the legs always sum to `amount`.
+pub fn split_amounts(splits: &[Split], amount: u64) -> Result<Vec<u64>> {
+    let mut amounts = Vec::with_capacity(splits.len());
+    let mut total: u64 = 0;
+
+    for (i, split) in splits.iter().enumerate() {
+        let split_amount = if i == splits.len() - 1 {
+            amount.saturating_sub(total)
+        } else {
+            (amount as u128)
+                .checked_mul(split.percentage as u128)
+                .ok_or(UnikError::Overflow)?
+                .checked_div(10000)
+                .ok_or(UnikError::Overflow)? as u64
+        };
+        total = total.checked_add(split_amount).ok_or(UnikError::Overflow)?;
+        amounts.push(split_amount);
+    }
+    Ok(amounts)
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct RegisterAlias<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = AliasAccount::SPACE,
         seeds = [b"alias", alias.as_bytes()],
-        bump,
-        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+        bump
     )]
     pub alias_account: Account<'info, AliasAccount>,
-
-    /// The stale route account - uses UncheckedAccount to bypass deserialization
-    /// CHECK: Manually validated in the instruction handler via PDA derivation and owner check
+    
     #[account(mut)]
-    pub route_account: UncheckedAccount<'info>,
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+
+    /// CHECK: Auction PDA - address enforced by seeds, must not exist
+    #[account(seeds = [b"auction", alias.as_bytes()], bump)]
+    pub auction: UncheckedAccount<'info>,
+}
 
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct UpdateAlias<'info> {
+    #[account(
+        mut,
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+    
     #[account(mut)]
     pub user: Signer<'info>,
 }
 
 #[derive(Accounts)]
 #[instruction(alias: String)]
-pub struct DeleteRouteConfig<'info> {
-    // Verify the alias exists and the user owns it
+pub struct DeleteAlias<'info> {
     #[account(
+        mut,
         seeds = [b"alias", alias.as_bytes()],
         bump,
         constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+        close = user,  // Refund rent to user
     )]
     pub alias_account: Account<'info, AliasAccount>,
 
-    // Close the route account and refund rent
+    /// CHECK: Route PDA - address enforced by seeds, closed in the handler if it exists
     #[account(
         mut,
         seeds = [b"route", alias.as_bytes()],
         bump,
-        close = user,
     )]
-    pub route_account: Account<'info, RouteAccount>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-}
+    pub route_account: UncheckedAccount<'info>,
 
-#[derive(Accounts)]
-#[instruction(alias: String, splits: Vec<Split>)]
-pub struct SetRouteConfig<'info> {
+    /// CHECK: ReferralPolicy PDA - address enforced by seeds, closed in the handler if it exists
     #[account(
         mut,
-        seeds = [b"route", alias.as_bytes()],
+        seeds = [b"referral", alias.as_bytes()],
         bump,
     )]
-    pub route_account: Account<'info, RouteAccount>,
-    
+    pub referral_policy: UncheckedAccount<'info>,
+
+    /// CHECK: MintFilter PDA - address enforced by seeds, closed in the handler if it exists
     #[account(
-        seeds = [b"alias", alias.as_bytes()],
+        mut,
+        seeds = [b"mints", alias.as_bytes()],
         bump,
-        constraint = alias_account.owner == user.key()
     )]
-    pub alias_account: Account<'info, AliasAccount>,
+    pub mint_filter: UncheckedAccount<'info>,
+
+    /// CHECK: Listing PDA - address enforced by seeds, closed in the handler if it exists
+    #[account(
+        mut,
+        seeds = [b"listing", alias.as_bytes()],
+        bump,
+    )]
+    pub listing: UncheckedAccount<'info>,
+
+    /// CHECK: Seller of a listing left by a previous owner - receives its rent; address checked against the listing
+    #[account(mut)]
+    pub listing_seller: Option<UncheckedAccount<'info>>,
     
     #[account(mut)]
     pub user: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = Config::SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ UnikError::Unauthorized)]
+    pub program: Program<'info, crate::program::UnikAnchor>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ UnikError::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    pub signer: Signer<'info>,
 }
 
 #[derive(Accounts)]
 #[instruction(alias: String)]
-pub struct InitRouteConfig<'info> {
+pub struct ComplianceFreeze<'info> {
+    // Grows a legacy alias to the current layout to hold the freeze fields. `Account` decodes before the
+    // realloc, so only aliases with unused string room get here; one at full length needs `migrate_account` first.
+    #[account(
+        mut,
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        realloc = AliasAccount::SPACE,
+        realloc::payer = authority,
+        realloc::zero = false,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.compliance_authority != Pubkey::default() @ UnikError::Unauthorized,
+        constraint = config.compliance_authority == authority.key() @ UnikError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct TokenizeAlias<'info> {
+    // Grows a legacy alias to the current layout to hold nft_mint. `Account` decodes before the
+    // realloc, so only aliases with unused string room get here; one at full length needs `migrate_account` first.
+    #[account(
+        mut,
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+        realloc = AliasAccount::SPACE,
+        realloc::payer = user,
+        realloc::zero = false,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
     #[account(
         init,
         payer = user,
-        space = 8 + 32 + 4 + (5 * 34) + 1 + 100, // Fixed space for max 5 splits
-        seeds = [b"route", alias.as_bytes()],
+        seeds = [b"alias_nft", alias.as_bytes()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = alias_account,
+        mint::token_program = token_program,
+        extensions::metadata_pointer::authority = alias_account,
+        extensions::metadata_pointer::metadata_address = nft_mint,
+        extensions::close_authority::authority = alias_account,
+        extensions::transfer_hook::authority = alias_account,
+        extensions::transfer_hook::program_id = crate::ID,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    /// CHECK: ExtraAccountMetaList PDA read by Token-2022 to build transfer hook calls; written in the handler
+    #[account(
+        init,
+        payer = user,
+        space = ExtraAccountMetaList::size_of(2)?,
+        seeds = [b"extra-account-metas", nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = nft_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub holder_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct DetokenizeAlias<'info> {
+    // The transfer hook keeps owner equal to the NFT holder
+    #[account(
+        mut,
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+        constraint = alias_account.nft_mint == nft_mint.key() @ UnikError::AliasNotTokenized,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"alias_nft", alias.as_bytes()],
+        bump,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    /// CHECK: ExtraAccountMetaList PDA - address enforced by seeds, closed in the handler
+    #[account(
+        mut,
+        seeds = [b"extra-account-metas", nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        token::mint = nft_mint,
+        token::authority = user,
+        token::token_program = token_program,
+    )]
+    pub holder_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token2022>,
+}
+
+/// Accounts of the SPL transfer hook `Execute` instruction, in its fixed order,
+/// followed by the extra accounts from the mint's ExtraAccountMetaList.
+#[derive(Accounts)]
+pub struct TransferHook<'info> {
+    #[account(token::mint = mint)]
+    pub source_token: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    pub mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    #[account(token::mint = mint)]
+    pub destination_token: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    /// CHECK: Source owner or delegate - already authorized by Token-2022
+    pub owner: UncheckedAccount<'info>,
+
+    /// CHECK: ExtraAccountMetaList PDA - address enforced by seeds
+    #[account(seeds = [b"extra-account-metas", mint.key().as_ref()], bump)]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+
+    // nft_mint is only ever set to the alias's own mint PDA
+    #[account(
+        mut,
+        constraint = alias_account.nft_mint == mint.key() @ UnikError::AliasNotTokenized,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    /// CHECK: Optional RouteAccount PDA - address enforced by seeds, reset to the new holder if it exists
+    #[account(
+        mut,
+        seeds = [b"route", alias_account.alias.as_bytes()],
+        bump,
+    )]
+    pub route_account: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct ListAlias<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = Listing::SPACE,
+        seeds = [b"listing", alias.as_bytes()],
         bump
     )]
-    pub route_account: Account<'info, RouteAccount>,
-    
+    pub listing: Account<'info, Listing>,
+
     #[account(
         seeds = [b"alias", alias.as_bytes()],
         bump,
-        constraint = alias_account.owner == user.key()
+        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
     )]
     pub alias_account: Account<'info, AliasAccount>,
-    
+
     #[account(mut)]
     pub user: Signer<'info>,
-    
+
+    /// CHECK: Seller of a listing left by a previous owner - receives its rent; address checked against the listing
+    #[account(mut)]
+    pub listing_seller: Option<UncheckedAccount<'info>>,
+
     pub system_program: Program<'info, System>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
 #[instruction(alias: String)]
-pub struct ExecuteTransfer<'info> {
+pub struct DelistAlias<'info> {
+    #[account(
+        mut,
+        seeds = [b"listing", alias.as_bytes()],
+        bump = listing.bump,
+        constraint = listing.seller == seller.key() @ UnikError::Unauthorized,
+        close = seller,
+    )]
+    pub listing: Account<'info, Listing>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct BuyAlias<'info> {
+    #[account(
+        mut,
+        seeds = [b"listing", alias.as_bytes()],
+        bump = listing.bump,
+        close = seller,
+    )]
+    pub listing: Account<'info, Listing>,
+
     #[account(
+        mut,
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    /// CHECK: Optional RouteAccount PDA - address enforced by seeds, reset to the buyer if it exists
+    #[account(
+        mut,
         seeds = [b"route", alias.as_bytes()],
         bump,
-        // CRIT-02: Validate route belongs to this alias
-        constraint = route_account.alias_ref == alias_account.key() @ UnikError::InvalidPDA,
     )]
-    pub route_account: Account<'info, RouteAccount>,
+    pub route_account: UncheckedAccount<'info>,
+
+    /// CHECK: The seller - receives the proceeds of SOL sales and the listing rent; address enforced against the listing
+    #[account(mut, address = listing.seller @ UnikError::InvalidRecipientAccount)]
+    pub seller: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    /// CHECK: Required for SOL sales when a royalty applies - checked against Config.treasury in the handler
+    #[account(mut)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+
+    /// Required for token sales
+    #[account(
+        mut,
+        constraint = buyer_token_account.owner == buyer.key() @ UnikError::InvalidUserTokenAccount,
+        constraint = buyer_token_account.mint == listing.mint @ UnikError::MintMismatch,
+    )]
+    pub buyer_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Required for token sales
+    #[account(
+        mut,
+        constraint = seller_token_account.owner == listing.seller @ UnikError::InvalidRecipientAccount,
+        constraint = seller_token_account.mint == listing.mint @ UnikError::MintMismatch,
+    )]
+    pub seller_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Required for token sales when a royalty applies - its owner is checked against Config.treasury
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == listing.mint @ UnikError::MintMismatch,
+    )]
+    pub treasury_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags and royalty read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct MakeOffer<'info> {
+    #[account(
+        init,
+        payer = buyer,
+        space = Offer::SPACE,
+        seeds = [b"offer", alias.as_bytes(), buyer.key().as_ref()],
+        bump
+    )]
+    pub offer: Account<'info, Offer>,
 
-    // CRIT-01 + CRIT-02: Reference alias account for is_active check and cross-validation
     #[account(
+        mut,
         seeds = [b"alias", alias.as_bytes()],
         bump,
     )]
     pub alias_account: Account<'info, AliasAccount>,
-    
+
     #[account(mut)]
-    pub user: Signer<'info>,
-    
+    pub buyer: Signer<'info>,
+
     pub system_program: Program<'info, System>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
 #[instruction(alias: String)]
-pub struct ExecuteTokenTransfer<'info> {
+pub struct MakeTokenOffer<'info> {
     #[account(
-        seeds = [b"route", alias.as_bytes()],
+        init,
+        payer = buyer,
+        space = Offer::SPACE,
+        seeds = [b"offer", alias.as_bytes(), buyer.key().as_ref()],
+        bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        init,
+        payer = buyer,
+        seeds = [b"offer_vault", offer.key().as_ref()],
         bump,
-        // CRIT-02: Validate route belongs to this alias
-        constraint = route_account.alias_ref == alias_account.key() @ UnikError::InvalidPDA,
+        token::mint = mint,
+        token::authority = offer,
     )]
-    pub route_account: Account<'info, RouteAccount>,
+    pub vault: Account<'info, TokenAccount>,
 
-    // CRIT-01 + CRIT-02: Reference alias account for is_active check and cross-validation
     #[account(
+        mut,
         seeds = [b"alias", alias.as_bytes()],
         bump,
     )]
     pub alias_account: Account<'info, AliasAccount>,
-    
+
     #[account(mut)]
-    pub user: Signer<'info>,
-    
-    /// The mint of the token being transferred - used to validate ATAs
+    pub buyer: Signer<'info>,
+
     pub mint: Account<'info, Mint>,
-    
+
     #[account(
         mut,
-        constraint = user_token_account.owner == user.key() @ UnikError::InvalidUserTokenAccount,
-        constraint = user_token_account.mint == mint.key() @ UnikError::MintMismatch,
+        constraint = buyer_token_account.owner == buyer.key() @ UnikError::InvalidUserTokenAccount,
+        constraint = buyer_token_account.mint == mint.key() @ UnikError::MintMismatch,
     )]
-    pub user_token_account: Account<'info, TokenAccount>,
-    
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
     pub token_program: Program<'info, Token>,
-    
+
     pub system_program: Program<'info, System>,
-}
 
-#[account]
-pub struct AliasAccount {
-    pub owner: Pubkey,
-    pub alias: String,         // Max 32 chars
-    pub metadata_uri: String,  // Max 200 chars
-    pub version: u64,          // Increments when owner changes (for contact safety)
-    pub is_active: bool,       // Can be deactivated without deletion
-    pub registered_at: i64,    // Unix timestamp of registration
-    pub bump: u8,
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
 }
 
-#[account]
-pub struct RouteAccount {
-    pub alias_ref: Pubkey,
-    pub splits: Vec<Split>,
-    pub bump: u8,
-}
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct CancelOffer<'info> {
+    #[account(
+        mut,
+        seeds = [b"offer", alias.as_bytes(), offer.buyer.as_ref()],
+        bump = offer.bump,
+        close = buyer,
+    )]
+    pub offer: Account<'info, Offer>,
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
-pub struct Split {
-    pub recipient: Pubkey,
-    pub percentage: u16, // Basis points (10000 = 100%)
-}
+    /// CHECK: The alias PDA, possibly deleted - address enforced by seeds, updated only if initialized
+    #[account(
+        mut,
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+    )]
+    pub alias_account: UncheckedAccount<'info>,
 
-#[error_code]
-pub enum UnikError {
-    #[msg("You are not the owner of this alias.")]
-    Unauthorized,
+    /// CHECK: The offer's buyer - receives the refund and the reclaimed rent; address enforced against the offer
+    #[account(mut, address = offer.buyer @ UnikError::InvalidRecipientAccount)]
+    pub buyer: UncheckedAccount<'info>,
+
+    /// Required for token offers
+    #[account(
+        mut,
+        seeds = [b"offer_vault", offer.key().as_ref()],
+        bump,
+    )]
+    pub vault: Option<Account<'info, TokenAccount>>,
+
+    /// Required for token offers
+    #[account(
+        mut,
+        constraint = buyer_token_account.owner == offer.buyer @ UnikError::InvalidUserTokenAccount,
+        constraint = buyer_token_account.mint == offer.mint @ UnikError::MintMismatch,
+    )]
+    pub buyer_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    /// The buyer, or the alias owner to decline
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct SettleOffer<'info> {
+    #[account(
+        mut,
+        seeds = [b"offer", alias.as_bytes(), offer.buyer.as_ref()],
+        bump = offer.bump,
+        close = buyer,
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        mut,
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    /// CHECK: Optional RouteAccount PDA - address enforced by seeds, reset to the buyer on acceptance if it exists
+    #[account(
+        mut,
+        seeds = [b"route", alias.as_bytes()],
+        bump,
+    )]
+    pub route_account: UncheckedAccount<'info>,
+
+    /// CHECK: Optional Listing PDA - address enforced by seeds, closed on acceptance if the seller listed it
+    #[account(
+        mut,
+        seeds = [b"listing", alias.as_bytes()],
+        bump,
+    )]
+    pub listing: UncheckedAccount<'info>,
+
+    /// CHECK: The offer's buyer - receives refunds and the reclaimed rent; address enforced against the offer
+    #[account(mut, address = offer.buyer @ UnikError::InvalidRecipientAccount)]
+    pub buyer: UncheckedAccount<'info>,
+
+    /// CHECK: Required to accept a SOL offer when a royalty applies - checked against Config.treasury in the handler
+    #[account(mut)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+
+    /// Required for token offers
+    #[account(
+        mut,
+        seeds = [b"offer_vault", offer.key().as_ref()],
+        bump,
+    )]
+    pub vault: Option<Account<'info, TokenAccount>>,
+
+    /// Required to accept a token offer
+    #[account(
+        mut,
+        constraint = seller_token_account.owner == authority.key() @ UnikError::InvalidRecipientAccount,
+        constraint = seller_token_account.mint == offer.mint @ UnikError::MintMismatch,
+    )]
+    pub seller_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Required to accept a token offer when a royalty applies - its owner is checked against Config.treasury
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == offer.mint @ UnikError::MintMismatch,
+    )]
+    pub treasury_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    /// The alias owner
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags and royalty read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct CreateAuction<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = Auction::SPACE,
+        seeds = [b"auction", alias.as_bytes()],
+        bump
+    )]
+    pub auction: Account<'info, Auction>,
+
+    /// CHECK: AliasAccount PDA - address enforced by seeds, must not exist
+    #[account(seeds = [b"alias", alias.as_bytes()], bump)]
+    pub alias_account: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.authority == authority.key() @ UnikError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct PlaceBid<'info> {
+    #[account(
+        mut,
+        seeds = [b"auction", alias.as_bytes()],
+        bump = auction.bump,
+    )]
+    pub auction: Account<'info, Auction>,
+
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+
+    /// CHECK: Required once there is a bid - the current highest bidder, refunded here; checked against the auction
+    #[account(mut)]
+    pub previous_bidder: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct SettleAuction<'info> {
+    #[account(
+        mut,
+        seeds = [b"auction", alias.as_bytes()],
+        bump = auction.bump,
+        close = creator,
+    )]
+    pub auction: Account<'info, Auction>,
+
+    /// Required when the auction has a winner - registered to them
+    #[account(
+        init,
+        payer = payer,
+        space = AliasAccount::SPACE,
+        seeds = [b"alias", alias.as_bytes()],
+        bump
+    )]
+    pub alias_account: Option<Account<'info, AliasAccount>>,
+
+    /// CHECK: Required when the auction has a winner - receives the winning bid; checked against Config.treasury
+    #[account(mut)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Receives the auction rent; address enforced against the auction
+    #[account(mut, address = auction.creator @ UnikError::InvalidRecipientAccount)]
+    pub creator: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags and treasury read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct CancelAuction<'info> {
+    #[account(
+        mut,
+        seeds = [b"auction", alias.as_bytes()],
+        bump = auction.bump,
+        close = authority,
+    )]
+    pub auction: Account<'info, Auction>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.authority == authority.key() @ UnikError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    /// CHECK: Any account of this program - identified by its discriminator in the handler
+    #[account(mut)]
+    pub account: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct MigrateRouteAccount<'info> {
+    /// The alias account - validated normally since its schema hasn't changed
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    /// The stale route account - uses UncheckedAccount to bypass deserialization
+    /// CHECK: Manually validated in the instruction handler via PDA derivation and owner check
+    #[account(mut)]
+    pub route_account: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct DeleteRouteConfig<'info> {
+    // Verify the alias exists and the user owns it
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    // Close the route account and refund rent
+    #[account(
+        mut,
+        seeds = [b"route", alias.as_bytes()],
+        bump,
+        close = user,
+    )]
+    pub route_account: Account<'info, RouteAccount>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String, splits: Vec<Split>)]
+pub struct SetRouteConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"route", alias.as_bytes()],
+        bump,
+    )]
+    pub route_account: Account<'info, RouteAccount>,
+    
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key()
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct InitRouteConfig<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = RouteAccount::SPACE,
+        seeds = [b"route", alias.as_bytes()],
+        bump
+    )]
+    pub route_account: Account<'info, RouteAccount>,
+    
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key()
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct SetSplitDelivery<'info> {
+    #[account(
+        mut,
+        seeds = [b"route", alias.as_bytes()],
+        bump,
+        constraint = route_account.alias_ref == alias_account.key() @ UnikError::InvalidPDA,
+    )]
+    pub route_account: Account<'info, RouteAccount>,
+
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    pub user: Signer<'info>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct AcceptSplit<'info> {
+    #[account(
+        mut,
+        seeds = [b"route", alias.as_bytes()],
+        bump,
+        constraint = route_account.alias_ref == alias_account.key() @ UnikError::InvalidPDA,
+    )]
+    pub route_account: Account<'info, RouteAccount>,
+
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    /// The split recipient giving consent - must sign
+    pub recipient: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct ExecuteTransfer<'info> {
+    #[account(
+        seeds = [b"route", alias.as_bytes()],
+        bump,
+        // CRIT-02: Validate route belongs to this alias
+        constraint = route_account.alias_ref == alias_account.key() @ UnikError::InvalidPDA,
+    )]
+    pub route_account: Account<'info, RouteAccount>,
+
+    // CRIT-01 + CRIT-02: Reference alias account for is_active check and cross-validation
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    /// CHECK: Optional PaymentLimits PDA for native SOL - address enforced by seeds, read only if initialized
+    #[account(
+        seeds = [b"limits", alias.as_bytes(), NATIVE_SOL_MINT.as_ref()],
+        bump,
+    )]
+    pub payment_limits: UncheckedAccount<'info>,
+
+    /// CHECK: Optional PaymentStats PDA for native SOL - address enforced by seeds, updated only if initialized
+    #[account(
+        mut,
+        seeds = [b"stats", alias.as_bytes(), NATIVE_SOL_MINT.as_ref()],
+        bump,
+    )]
+    pub payment_stats: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Solana Pay style reference key - never read or written, only indexed with the transaction
+    pub reference: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Receipt PDA at [b"receipt", reference] - created in the handler when passed; requires `reference`
+    #[account(mut)]
+    pub receipt: Option<UncheckedAccount<'info>>,
+
+    /// Required when a recipient prefers wrapped SOL
+    pub token_program: Option<Program<'info, Token>>,
+
+    /// Required when `referral_bps` is non-zero
+    #[account(
+        seeds = [b"referral", alias.as_bytes()],
+        bump = referral_policy.bump,
+    )]
+    pub referral_policy: Option<Account<'info, ReferralPolicy>>,
+
+    /// CHECK: Referrer wallet - checked against referral_policy in the handler, receives the referral share
+    #[account(mut)]
+    pub referrer: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String, currency: [u8; 3])]
+pub struct ExecuteTransferFiat<'info> {
+    pub transfer: ExecuteTransfer<'info>,
+
+    #[account(
+        seeds = [b"fiat_feed", alias.as_bytes(), currency.as_ref()],
+        bump = fiat_feed.bump,
+    )]
+    pub fiat_feed: Account<'info, FiatPriceFeed>,
+
+    /// CHECK: Pyth-format price account - address pinned by fiat_feed, parsed in the handler
+    #[account(address = fiat_feed.price_feed @ UnikError::InvalidPriceFeed)]
+    pub price_feed: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String, currency: [u8; 3])]
+pub struct SetFiatPriceFeed<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = FiatPriceFeed::SPACE,
+        seeds = [b"fiat_feed", alias.as_bytes(), currency.as_ref()],
+        bump
+    )]
+    pub fiat_feed: Account<'info, FiatPriceFeed>,
+
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String, amount: u64, mint: Pubkey)]
+pub struct QuotePayment<'info> {
+    #[account(
+        seeds = [b"route", alias.as_bytes()],
+        bump,
+        constraint = route_account.alias_ref == alias_account.key() @ UnikError::InvalidPDA,
+    )]
+    pub route_account: Account<'info, RouteAccount>,
+
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    /// CHECK: Optional PaymentLimits PDA for the quoted mint - address enforced by seeds, read only if initialized
+    #[account(
+        seeds = [b"limits", alias.as_bytes(), mint.as_ref()],
+        bump,
+    )]
+    pub payment_limits: UncheckedAccount<'info>,
+
+    /// Required when quoting a token payment, to apply the decimals-aware default minimum
+    #[account(constraint = token_mint.key() == mint @ UnikError::MintMismatch)]
+    pub token_mint: Option<Account<'info, Mint>>,
+
+    /// CHECK: Optional MintFilter PDA - address enforced by seeds, read only if initialized
+    #[account(seeds = [b"mints", alias.as_bytes()], bump)]
+    pub mint_filter: UncheckedAccount<'info>,
+
+    /// Required when quoting a referral
+    #[account(
+        seeds = [b"referral", alias.as_bytes()],
+        bump = referral_policy.bump,
+    )]
+    pub referral_policy: Option<Account<'info, ReferralPolicy>>,
+
+    /// CHECK: Referrer wallet - checked against referral_policy in the handler
+    pub referrer: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct ExecuteTokenTransfer<'info> {
+    #[account(
+        seeds = [b"route", alias.as_bytes()],
+        bump,
+        // CRIT-02: Validate route belongs to this alias
+        constraint = route_account.alias_ref == alias_account.key() @ UnikError::InvalidPDA,
+    )]
+    pub route_account: Account<'info, RouteAccount>,
+
+    // CRIT-01 + CRIT-02: Reference alias account for is_active check and cross-validation
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    /// The mint of the token being transferred - used to validate ATAs
+    pub mint: Account<'info, Mint>,
+
+    /// CHECK: Optional PaymentLimits PDA for this mint - address enforced by seeds, read only if initialized
+    #[account(
+        seeds = [b"limits", alias.as_bytes(), mint.key().as_ref()],
+        bump,
+    )]
+    pub payment_limits: UncheckedAccount<'info>,
+
+    /// CHECK: Optional PaymentStats PDA for this mint - address enforced by seeds, updated only if initialized
+    #[account(
+        mut,
+        seeds = [b"stats", alias.as_bytes(), mint.key().as_ref()],
+        bump,
+    )]
+    pub payment_stats: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key() @ UnikError::InvalidUserTokenAccount,
+        constraint = user_token_account.mint == mint.key() @ UnikError::MintMismatch,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Solana Pay style reference key - never read or written, only indexed with the transaction
+    pub reference: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Receipt PDA at [b"receipt", reference] - created in the handler when passed; requires `reference`
+    #[account(mut)]
+    pub receipt: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Temporary wSOL account at [b"unwrap", user] - required when paying wSOL to recipients
+    /// preferring native SOL; created and closed within the instruction
+    #[account(mut)]
+    pub unwrap_account: Option<UncheckedAccount<'info>>,
+
+    /// Required when `referral_bps` is non-zero
+    #[account(
+        seeds = [b"referral", alias.as_bytes()],
+        bump = referral_policy.bump,
+    )]
+    pub referral_policy: Option<Account<'info, ReferralPolicy>>,
+
+    /// CHECK: Referrer wallet - checked against referral_policy in the handler
+    pub referrer: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Referrer's ATA for the mint - address validated in the handler
+    #[account(mut)]
+    pub referrer_token_account: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+
+    /// CHECK: Optional MintFilter PDA - address enforced by seeds, read only if initialized
+    #[account(seeds = [b"mints", alias.as_bytes()], bump)]
+    pub mint_filter: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BatchExecuteTokenTransfer<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// The mint of the token being transferred - used to validate ATAs
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key() @ UnikError::InvalidUserTokenAccount,
+        constraint = user_token_account.mint == mint.key() @ UnikError::MintMismatch,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: Solana Pay style reference key shared by every entry - never read or written
+    pub reference: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String, mint: Pubkey)]
+pub struct SetPaymentLimits<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = PaymentLimits::SPACE,
+        seeds = [b"limits", alias.as_bytes(), mint.as_ref()],
+        bump
+    )]
+    pub payment_limits: Account<'info, PaymentLimits>,
+
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String, mint: Pubkey)]
+pub struct DeletePaymentLimits<'info> {
+    #[account(
+        mut,
+        seeds = [b"limits", alias.as_bytes(), mint.as_ref()],
+        bump,
+        close = user,
+    )]
+    pub payment_limits: Account<'info, PaymentLimits>,
+
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct SetReferralPolicy<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = ReferralPolicy::SPACE,
+        seeds = [b"referral", alias.as_bytes()],
+        bump
+    )]
+    pub referral_policy: Account<'info, ReferralPolicy>,
+
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct DeleteReferralPolicy<'info> {
+    #[account(
+        mut,
+        seeds = [b"referral", alias.as_bytes()],
+        bump = referral_policy.bump,
+        close = user,
+    )]
+    pub referral_policy: Account<'info, ReferralPolicy>,
+
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct SetMintFilter<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = MintFilter::SPACE,
+        seeds = [b"mints", alias.as_bytes()],
+        bump
+    )]
+    pub mint_filter: Account<'info, MintFilter>,
+
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct DeleteMintFilter<'info> {
+    #[account(
+        mut,
+        seeds = [b"mints", alias.as_bytes()],
+        bump = mint_filter.bump,
+        close = user,
+    )]
+    pub mint_filter: Account<'info, MintFilter>,
+
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String, mint: Pubkey)]
+pub struct InitPaymentStats<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = PaymentStats::SPACE,
+        seeds = [b"stats", alias.as_bytes(), mint.as_ref()],
+        bump
+    )]
+    pub payment_stats: Account<'info, PaymentStats>,
+
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.owner == user.key() @ UnikError::Unauthorized,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefundPayment<'info> {
+    #[account(
+        mut,
+        seeds = [b"receipt", receipt.reference.as_ref()],
+        bump = receipt.bump,
+    )]
+    pub receipt: Account<'info, Receipt>,
+
+    /// CHECK: The original payer - receives SOL refunds; address enforced against the receipt
+    #[account(mut, address = receipt.payer @ UnikError::InvalidRecipientAccount)]
+    pub payer: UncheckedAccount<'info>,
+
+    /// Required for token refunds - the payer's token account for the receipt mint
+    #[account(
+        mut,
+        constraint = payer_token_account.owner == receipt.payer @ UnikError::InvalidUserTokenAccount,
+        constraint = payer_token_account.mint == receipt.mint @ UnikError::MintMismatch,
+    )]
+    pub payer_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseReceipt<'info> {
+    #[account(
+        mut,
+        seeds = [b"receipt", receipt.reference.as_ref()],
+        bump = receipt.bump,
+        constraint = receipt.payer == payer.key() @ UnikError::Unauthorized,
+        close = payer,
+    )]
+    pub receipt: Account<'info, Receipt>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String, escrow_id: u64)]
+pub struct CreateEscrow<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = Escrow::SPACE,
+        seeds = [b"escrow", alias.as_bytes(), user.key().as_ref(), &escrow_id.to_le_bytes()],
+        bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        mut,
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    /// CHECK: Optional PaymentLimits PDA for native SOL - address enforced by seeds, read only if initialized
+    #[account(
+        seeds = [b"limits", alias.as_bytes(), NATIVE_SOL_MINT.as_ref()],
+        bump,
+    )]
+    pub payment_limits: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String, escrow_id: u64)]
+pub struct CreateTokenEscrow<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = Escrow::SPACE,
+        seeds = [b"escrow", alias.as_bytes(), user.key().as_ref(), &escrow_id.to_le_bytes()],
+        bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"escrow_vault", escrow.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = escrow,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    /// CHECK: Optional PaymentLimits PDA for this mint - address enforced by seeds, read only if initialized
+    #[account(
+        seeds = [b"limits", alias.as_bytes(), mint.key().as_ref()],
+        bump,
+    )]
+    pub payment_limits: UncheckedAccount<'info>,
+
+    /// CHECK: Optional MintFilter PDA - address enforced by seeds, read only if initialized
+    #[account(seeds = [b"mints", alias.as_bytes()], bump)]
+    pub mint_filter: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key() @ UnikError::InvalidUserTokenAccount,
+        constraint = user_token_account.mint == mint.key() @ UnikError::MintMismatch,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct SettleEscrow<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", alias.as_bytes(), escrow.payer.as_ref(), &escrow.escrow_id.to_le_bytes()],
+        bump = escrow.bump,
+        close = payer,
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    /// Required to release - the route the funds are distributed through
+    #[account(
+        seeds = [b"route", alias.as_bytes()],
+        bump,
+        constraint = route_account.alias_ref == alias_account.key() @ UnikError::InvalidPDA,
+    )]
+    pub route_account: Option<Account<'info, RouteAccount>>,
+
+    /// CHECK: The alias PDA, possibly deleted since the escrow was made - address enforced by seeds, updated only if initialized
+    #[account(
+        mut,
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+        constraint = alias_account.key() == escrow.alias_ref @ UnikError::InvalidPDA,
+    )]
+    pub alias_account: UncheckedAccount<'info>,
+
+    /// CHECK: Optional PaymentStats PDA for the escrow mint - address enforced by seeds, updated only if initialized
+    #[account(
+        mut,
+        seeds = [b"stats", alias.as_bytes(), escrow.mint.as_ref()],
+        bump,
+    )]
+    pub payment_stats: UncheckedAccount<'info>,
+
+    /// CHECK: The escrow payer - receives refunds and the reclaimed rent; address enforced against the escrow
+    #[account(mut, address = escrow.payer @ UnikError::InvalidRecipientAccount)]
+    pub payer: UncheckedAccount<'info>,
+
+    /// Required for token escrows
+    #[account(
+        mut,
+        seeds = [b"escrow_vault", escrow.key().as_ref()],
+        bump,
+    )]
+    pub vault: Option<Account<'info, TokenAccount>>,
+
+    /// Required to refund a token escrow
+    #[account(
+        mut,
+        constraint = payer_token_account.owner == escrow.payer @ UnikError::InvalidUserTokenAccount,
+        constraint = payer_token_account.mint == escrow.mint @ UnikError::MintMismatch,
+    )]
+    pub payer_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    /// Payer, alias owner or arbiter depending on the instruction
+    pub authority: Signer<'info>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct CreateSubscription<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = Subscription::SPACE,
+        seeds = [b"subscription", alias.as_bytes(), user.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub subscription: Account<'info, Subscription>,
+
+    #[account(
+        mut,
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    /// CHECK: Optional PaymentLimits PDA for this mint - address enforced by seeds, read only if initialized
+    #[account(
+        seeds = [b"limits", alias.as_bytes(), mint.key().as_ref()],
+        bump,
+    )]
+    pub payment_limits: UncheckedAccount<'info>,
+
+    /// CHECK: Optional MintFilter PDA - address enforced by seeds, read only if initialized
+    #[account(seeds = [b"mints", alias.as_bytes()], bump)]
+    pub mint_filter: UncheckedAccount<'info>,
+
+    /// CHECK: Program-wide PDA approved as SPL delegate on subscriber token accounts; holds no data
+    #[account(
+        seeds = [b"subscription_delegate"],
+        bump,
+    )]
+    pub subscription_delegate: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key() @ UnikError::InvalidUserTokenAccount,
+        constraint = user_token_account.mint == mint.key() @ UnikError::MintMismatch,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct CollectSubscription<'info> {
+    #[account(
+        mut,
+        seeds = [b"subscription", alias.as_bytes(), subscription.payer.as_ref(), subscription.mint.as_ref()],
+        bump = subscription.bump,
+        constraint = subscription.alias_ref == alias_account.key() @ UnikError::InvalidPDA,
+    )]
+    pub subscription: Account<'info, Subscription>,
+
+    #[account(
+        seeds = [b"route", alias.as_bytes()],
+        bump,
+        constraint = route_account.alias_ref == alias_account.key() @ UnikError::InvalidPDA,
+    )]
+    pub route_account: Account<'info, RouteAccount>,
+
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    /// CHECK: Optional PaymentStats PDA for the subscription mint - address enforced by seeds, updated only if initialized
+    #[account(
+        mut,
+        seeds = [b"stats", alias.as_bytes(), subscription.mint.as_ref()],
+        bump,
+    )]
+    pub payment_stats: UncheckedAccount<'info>,
+
+    /// CHECK: Program-wide subscription delegate PDA - signs the pull
+    #[account(
+        seeds = [b"subscription_delegate"],
+        bump,
+    )]
+    pub subscription_delegate: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = subscription.payer_token_account @ UnikError::InvalidUserTokenAccount,
+    )]
+    pub payer_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct CancelSubscription<'info> {
+    #[account(
+        mut,
+        seeds = [b"subscription", alias.as_bytes(), subscription.payer.as_ref(), subscription.mint.as_ref()],
+        bump = subscription.bump,
+        close = payer,
+    )]
+    pub subscription: Account<'info, Subscription>,
+
+    /// CHECK: The alias PDA, possibly deleted - address enforced by seeds, updated only if initialized
+    #[account(
+        mut,
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+    )]
+    pub alias_account: UncheckedAccount<'info>,
+
+    /// CHECK: The subscription payer - receives the reclaimed rent; address enforced against the subscription
+    #[account(mut, address = subscription.payer @ UnikError::InvalidRecipientAccount)]
+    pub payer: UncheckedAccount<'info>,
+
+    /// CHECK: Program-wide subscription delegate PDA - its approval is reduced by the unused allowance
+    #[account(
+        seeds = [b"subscription_delegate"],
+        bump,
+    )]
+    pub subscription_delegate: UncheckedAccount<'info>,
+
+    /// The subscribed token account, unless it has been closed
+    #[account(
+        mut,
+        address = subscription.payer_token_account @ UnikError::InvalidUserTokenAccount,
+    )]
+    pub payer_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    /// The payer, or the alias owner to end the subscription
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String, stream_id: u64)]
+pub struct CreateStream<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = Stream::SPACE,
+        seeds = [b"stream", alias.as_bytes(), user.key().as_ref(), &stream_id.to_le_bytes()],
+        bump
+    )]
+    pub stream: Account<'info, Stream>,
+
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"stream_vault", stream.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = stream,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    /// CHECK: Optional PaymentLimits PDA for this mint - address enforced by seeds, read only if initialized
+    #[account(
+        seeds = [b"limits", alias.as_bytes(), mint.key().as_ref()],
+        bump,
+    )]
+    pub payment_limits: UncheckedAccount<'info>,
+
+    /// CHECK: Optional MintFilter PDA - address enforced by seeds, read only if initialized
+    #[account(seeds = [b"mints", alias.as_bytes()], bump)]
+    pub mint_filter: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key() @ UnikError::InvalidUserTokenAccount,
+        constraint = user_token_account.mint == mint.key() @ UnikError::MintMismatch,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct WithdrawStream<'info> {
+    #[account(
+        mut,
+        seeds = [b"stream", alias.as_bytes(), stream.sender.as_ref(), &stream.stream_id.to_le_bytes()],
+        bump = stream.bump,
+        constraint = stream.alias_ref == alias_account.key() @ UnikError::InvalidPDA,
+    )]
+    pub stream: Account<'info, Stream>,
+
+    #[account(
+        mut,
+        seeds = [b"stream_vault", stream.key().as_ref()],
+        bump,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"route", alias.as_bytes()],
+        bump,
+        constraint = route_account.alias_ref == alias_account.key() @ UnikError::InvalidPDA,
+    )]
+    pub route_account: Account<'info, RouteAccount>,
+
+    #[account(
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+    )]
+    pub alias_account: Account<'info, AliasAccount>,
+
+    /// CHECK: Optional PaymentStats PDA for the stream mint - address enforced by seeds, updated only if initialized
+    #[account(
+        mut,
+        seeds = [b"stats", alias.as_bytes(), stream.mint.as_ref()],
+        bump,
+    )]
+    pub payment_stats: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(alias: String)]
+pub struct CancelStream<'info> {
+    #[account(
+        mut,
+        seeds = [b"stream", alias.as_bytes(), stream.sender.as_ref(), &stream.stream_id.to_le_bytes()],
+        bump = stream.bump,
+        constraint = stream.alias_ref == alias_account.key() @ UnikError::InvalidPDA,
+        close = sender,
+    )]
+    pub stream: Account<'info, Stream>,
+
+    #[account(
+        mut,
+        seeds = [b"stream_vault", stream.key().as_ref()],
+        bump,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    /// CHECK: The route PDA, possibly deleted - address enforced by seeds, read only if initialized
+    #[account(
+        seeds = [b"route", alias.as_bytes()],
+        bump,
+    )]
+    pub route_account: UncheckedAccount<'info>,
+
+    /// CHECK: The alias PDA, possibly deleted - address enforced by seeds, updated only if initialized
+    #[account(
+        mut,
+        seeds = [b"alias", alias.as_bytes()],
+        bump,
+    )]
+    pub alias_account: UncheckedAccount<'info>,
+
+    /// CHECK: Optional PaymentStats PDA for the stream mint - address enforced by seeds, updated only if initialized
+    #[account(
+        mut,
+        seeds = [b"stats", alias.as_bytes(), stream.mint.as_ref()],
+        bump,
+    )]
+    pub payment_stats: UncheckedAccount<'info>,
+
+    /// CHECK: The stream sender - receives the vault's and the stream's rent; address enforced against the stream
+    #[account(mut, address = stream.sender @ UnikError::InvalidRecipientAccount)]
+    pub sender: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = sender_token_account.owner == stream.sender @ UnikError::InvalidUserTokenAccount,
+        constraint = sender_token_account.mint == stream.mint @ UnikError::MintMismatch,
+    )]
+    pub sender_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: Optional Config PDA - address enforced by seeds, pause flags read only if initialized
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+
+    /// The sender, or the alias owner to end the stream
+    pub authority: Signer<'info>,
+}
+
+/// Accounts carrying a `schema_version`, bumped whenever their layout changes.
+/// Layouts only grow by appending fields whose zero value is the right default, so an account written
+/// by any older version decodes as the current layout once zero-padded to SPACE (see `upgrade_layout`).
+/// Layout history (version 0 = written before schema_version existed):
+/// - every account v1: v0 + schema_version
+/// - RouteAccount v0: alias_ref, splits, bump; later consented_mask, then sol_delivery were appended
+/// - AliasAccount v2: v1 + freeze_reason, frozen_at, frozen_by
+/// - AliasAccount v3: v2 + nft_mint
+/// - AliasAccount v4: v3 + open_obligations
+/// - Config v2: v1 + compliance_authority
+/// - Config v3: v2 + treasury, royalty_bps
+pub trait VersionedAccount: AccountSerialize + AccountDeserialize + Discriminator {
+    const SCHEMA_VERSION: u8;
+    /// Size allocated for new accounts: the current layout at its maximum
+    const SPACE: usize;
+    fn schema_version(&self) -> u8;
+    fn set_schema_version(&mut self, schema_version: u8);
+}
+
+macro_rules! impl_versioned_account {
+    ($($account:ty => $version:expr, $space:expr;)*) => {
+        $(impl VersionedAccount for $account {
+            const SCHEMA_VERSION: u8 = $version;
+            const SPACE: usize = $space;
+            fn schema_version(&self) -> u8 {
+                self.schema_version
+            }
+            fn set_schema_version(&mut self, schema_version: u8) {
+                self.schema_version = schema_version;
+            }
+        })*
+    };
+}
+
+impl_versioned_account! {
+    // 8 (discriminator) + 32 (owner) + 4+32 (alias string) + 4+200 (metadata_uri) + 8 (version) + 1 (is_active) + 8 (registered_at) + 1 (bump) + 1 (schema_version)
+    // + 2 (freeze_reason) + 8 (frozen_at) + 32 (frozen_by) + 32 (nft_mint) + 4 (open_obligations)
+    AliasAccount => 4, 8 + 32 + 4 + 32 + 4 + 200 + 8 + 1 + 8 + 1 + 1 + 2 + 8 + 32 + 32 + 4;
+    // 8 (discriminator) + 32 (alias_ref) + 4+(5*34) (splits) + 1 (bump) + 1 (consented_mask)
+    // + 4+5 (sol_delivery) + 1 (schema_version) + 100 (padding)
+    RouteAccount => 1, 8 + 32 + 4 + (5 * 34) + 1 + 1 + 4 + 5 + 1 + 100;
+    // 8 (discriminator) + 32 (alias_ref) + 32 (mint) + 8 (min_amount) + 1+8 (max_amount) + 1 (bump) + 1 (schema_version)
+    PaymentLimits => 1, 8 + 32 + 32 + 8 + 1 + 8 + 1 + 1;
+    // 8 (discriminator) + 32 (alias_ref) + 2 (max_bps) + 1 (open) + 4+(MAX_REFERRERS*32) (allowed_referrers) + 1 (bump) + 1 (schema_version)
+    ReferralPolicy => 1, 8 + 32 + 2 + 1 + 4 + (MAX_REFERRERS * 32) + 1 + 1;
+    // 8 (discriminator) + 32 (alias_ref) + 3 (currency) + 32 (price_feed) + 8 (max_staleness) + 2 (max_confidence_bps) + 1 (bump) + 1 (schema_version)
+    FiatPriceFeed => 1, 8 + 32 + 3 + 32 + 8 + 2 + 1 + 1;
+    // 8 (discriminator) + 32 (alias_ref) + 32 (mint) + 16 (total_volume) + 8 (payment_count)
+    // + 8 (last_payment_at) + 8 (unique_payers) + 128 (payer_filter) + 8 (tracking_since) + 1 (bump) + 1 (schema_version)
+    PaymentStats => 1, 8 + 32 + 32 + 16 + 8 + 8 + 8 + 128 + 8 + 1 + 1;
+    // 8 (discriminator) + 32 (alias_ref) + 32 (payer) + 32 (mint) + 8 (amount) + 4+(5*34) (splits)
+    // + 8 (refunded_amount) + 1 (is_refunded) + 32 (reference) + 8 (created_at) + 1 (bump) + 1 (schema_version)
+    Receipt => 1, 8 + 32 + 32 + 32 + 8 + 4 + (5 * 34) + 8 + 1 + 32 + 8 + 1 + 1;
+    // 8 (discriminator) + 32 (alias_ref) + 32 (payer) + 32 (mint) + 8 (amount) + 8 (escrow_id)
+    // + 1+32 (arbiter) + 8 (release_after) + 8 (created_at) + 1 (bump) + 1 (schema_version)
+    Escrow => 1, 8 + 32 + 32 + 32 + 8 + 8 + 1 + 32 + 8 + 8 + 1 + 1;
+    // 8 (discriminator) + 32 (payer) + 32 (alias_ref) + 32 (mint) + 32 (payer_token_account)
+    // + 8 (amount) + 8 (period) + 8 (next_due) + 8 (allowance) + 8 (created_at) + 1 (bump) + 1 (schema_version)
+    Subscription => 1, 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1 + 1;
+    // 8 (discriminator) + 32 (sender) + 32 (alias_ref) + 32 (mint) + 8 (stream_id)
+    // + 8 (total_amount) + 8 (withdrawn) + 8 (start_ts) + 8 (end_ts) + 1 (bump) + 1 (schema_version)
+    Stream => 1, 8 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1 + 1;
+    // 8 (discriminator) + 32 (authority) + 32 (pause_authority) + 1 (paused_flags) + 1 (bump) + 1 (schema_version)
+    // + 32 (compliance_authority)
+    // + 32 (treasury) + 2 (royalty_bps)
+    Config => 3, 8 + 32 + 32 + 1 + 1 + 1 + 32 + 32 + 2;
+    // 8 (discriminator) + 32 (alias_ref) + 1 (mode) + 4+(MAX_FILTER_MINTS*32) (mints) + 1 (bump) + 1 (schema_version)
+    MintFilter => 1, 8 + 32 + 1 + 4 + (MAX_FILTER_MINTS * 32) + 1 + 1;
+    // 8 (discriminator) + 32 (alias_ref) + 32 (seller) + 32 (mint) + 8 (price) + 8 (created_at) + 1 (bump) + 1 (schema_version)
+    Listing => 1, 8 + 32 + 32 + 32 + 8 + 8 + 1 + 1;
+    // 8 (discriminator) + 32 (alias_ref) + 32 (buyer) + 32 (mint) + 8 (amount) + 8 (created_at) + 1 (bump) + 1 (schema_version)
+    Offer => 1, 8 + 32 + 32 + 32 + 8 + 8 + 1 + 1;
+    // 8 (discriminator) + 4+32 (alias string) + 32 (creator) + 8 (reserve_price) + 8 (start_ts) + 8 (end_ts) + 8 (extension)
+    // + 32 (highest_bidder) + 8 (highest_bid) + 1 (bump) + 1 (schema_version)
+    Auction => 1, 8 + 4 + 32 + 32 + 8 + 8 + 8 + 8 + 32 + 8 + 1 + 1;
+}
+
+/// Protocol-wide settings. PDA: [b"config"] - while it doesn't exist nothing is paused.
+#[account]
+pub struct Config {
+    pub authority: Pubkey,         // Manages the authorities below
+    pub pause_authority: Pubkey,
+    pub paused_flags: u8,          // PAUSE_* bits
+    pub bump: u8,
+    pub schema_version: u8,        // See VersionedAccount
+    pub compliance_authority: Pubkey, // May freeze aliases; default = nobody
+    pub treasury: Pubkey,          // Receives marketplace royalties; default = no royalty
+    pub royalty_bps: u16,          // Share of each alias sale, at most MAX_ROYALTY_BPS
+}
+
+impl Config {
+    /// Protocol royalty owed on an alias sale for `price`
+    pub fn royalty(&self, price: u64) -> Result<u64> {
+        if self.treasury == Pubkey::default() {
+            return Ok(0);
+        }
+        let royalty = (price as u128)
+            .checked_mul(self.royalty_bps as u128)
+            .ok_or(UnikError::Overflow)?
+            / 10000;
+        Ok(royalty as u64)
+    }
+}
+
+#[account]
+pub struct AliasAccount {
+    pub owner: Pubkey,
+    pub alias: String,         // Max 32 chars
+    pub metadata_uri: String,  // Max 200 chars
+    pub version: u64,          // Increments when owner changes (for contact safety)
+    pub is_active: bool,       // Can be deactivated without deletion
+    pub registered_at: i64,    // Unix timestamp of registration
+    pub bump: u8,
+    pub schema_version: u8,        // See VersionedAccount
+    pub freeze_reason: u16,        // Compliance reason code; 0 = not frozen
+    pub frozen_at: i64,
+    pub frozen_by: Pubkey,
+    pub nft_mint: Pubkey,          // Alias NFT while tokenized (owner follows its holder); default = not tokenized
+    pub open_obligations: u32,     // Open escrows, streams, subscriptions and offers; deletion waits for 0
+}
+
+#[account]
+pub struct RouteAccount {
+    pub alias_ref: Pubkey,
+    pub splits: Vec<Split>,
+    pub bump: u8,
+    pub consented_mask: u8,    // Bit i set = recipient of splits[i] signed accept_split
+    pub sol_delivery: Vec<SolDelivery>, // Per split index; empty = every split AsPaid
+    pub schema_version: u8,    // See VersionedAccount
+}
+
+impl RouteAccount {
+    pub fn sol_delivery_for(&self, split_index: usize) -> SolDelivery {
+        self.sol_delivery.get(split_index).copied().unwrap_or(SolDelivery::AsPaid)
+    }
+
+    /// SHA-256 of the borsh-serialized splits (Vec<Split>, including its length prefix)
+    pub fn splits_hash(&self) -> Result<[u8; 32]> {
+        Ok(hash(&self.splits.try_to_vec()?).to_bytes())
+    }
+}
+
+/// How a split recipient wants to receive SOL-denominated payments
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SolDelivery {
+    AsPaid,   // Native for execute_transfer, wSOL for execute_token_transfer
+    Native,   // wSOL payments are unwrapped to lamports
+    Wrapped,  // Native payments are wrapped into the recipient's wSOL ATA
+}
+
+#[account]
+pub struct PaymentLimits {
+    pub alias_ref: Pubkey,
+    pub mint: Pubkey,              // NATIVE_SOL_MINT for SOL
+    pub min_amount: u64,           // In base units of the mint
+    pub max_amount: Option<u64>,
+    pub bump: u8,
+    pub schema_version: u8,        // See VersionedAccount
+}
+
+/// Referral fees an alias accepts on its payments.
+/// PDA: [b"referral", alias]
+#[account]
+pub struct ReferralPolicy {
+    pub alias_ref: Pubkey,
+    pub max_bps: u16,              // Max share of a payment a payer may route to a referrer
+    pub open: bool,                // Accept any referrer, not just allowed_referrers
+    pub allowed_referrers: Vec<Pubkey>,
+    pub bump: u8,
+    pub schema_version: u8,        // See VersionedAccount
+}
+
+/// An alias offered for sale at a fixed price. PDA: [b"listing", alias]
+#[account]
+pub struct Listing {
+    pub alias_ref: Pubkey,
+    pub seller: Pubkey,            // Owner when listed; the listing is void once the owner changes
+    pub mint: Pubkey,              // NATIVE_SOL_MINT: priced in lamports
+    pub price: u64,
+    pub created_at: i64,
+    pub bump: u8,
+    pub schema_version: u8,        // See VersionedAccount
+}
+
+/// Funds a buyer holds out for an alias until its owner accepts or the buyer cancels.
+/// PDA: [b"offer", alias, buyer]; token funds sit in [b"offer_vault", offer].
+#[account]
+pub struct Offer {
+    pub alias_ref: Pubkey,
+    pub buyer: Pubkey,
+    pub mint: Pubkey,              // NATIVE_SOL_MINT: lamports are held by this account
+    pub amount: u64,
+    pub created_at: i64,
+    pub bump: u8,
+    pub schema_version: u8,        // See VersionedAccount
+}
+
+/// English auction for an unregistered alias, bid in lamports held by this account.
+/// PDA: [b"auction", alias] - while it exists the alias can't be registered directly.
+#[account]
+pub struct Auction {
+    pub alias: String,             // Max 32 chars
+    pub creator: Pubkey,           // Config authority that opened it; gets the rent back
+    pub reserve_price: u64,
+    pub start_ts: i64,
+    pub end_ts: i64,               // Pushed back by late bids, see `extension`
+    pub extension: i64,            // Minimum time left after any bid
+    pub highest_bidder: Pubkey,    // default = no bids yet
+    pub highest_bid: u64,
+    pub bump: u8,
+    pub schema_version: u8,        // See VersionedAccount
+}
+
+impl Auction {
+    /// Smallest amount the next bid must reach
+    pub fn min_bid(&self) -> Result<u64> {
+        if self.highest_bidder == Pubkey::default() {
+            return Ok(self.reserve_price);
+        }
+        let increment = (self.highest_bid as u128 * MIN_BID_INCREMENT_BPS as u128 / 10000).max(1) as u64;
+        Ok(self.highest_bid.checked_add(increment).ok_or(UnikError::Overflow)?.max(self.reserve_price))
+    }
+}
+
+/// SPL mints an alias accepts, readable by payment pages to list accepted tokens.
+/// PDA: [b"mints", alias] - without it every mint is accepted.
+#[account]
+pub struct MintFilter {
+    pub alias_ref: Pubkey,
+    pub mode: MintFilterMode,
+    pub mints: Vec<Pubkey>,
+    pub bump: u8,
+    pub schema_version: u8,        // See VersionedAccount
+}
+
+impl MintFilter {
+    pub fn accepts(&self, mint: &Pubkey) -> bool {
+        match self.mode {
+            MintFilterMode::Allow => self.mints.contains(mint),
+            MintFilterMode::Deny => !self.mints.contains(mint),
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MintFilterMode {
+    Allow,   // Only the listed mints
+    Deny,    // Any mint except the listed ones
+}
+
+/// Price feed an alias accepts for fiat-denominated SOL payments.
+/// PDA: [b"fiat_feed", alias, currency]
+#[account]
+pub struct FiatPriceFeed {
+    pub alias_ref: Pubkey,
+    pub currency: [u8; 3],         // ISO 4217 code, e.g. b"USD"
+    pub price_feed: Pubkey,        // Pyth-format SOL/<currency> price account
+    pub max_staleness: i64,        // Seconds since the feed's publish time
+    pub max_confidence_bps: u16,   // Max confidence interval relative to price
+    pub bump: u8,
+    pub schema_version: u8,        // See VersionedAccount
+}
+
+/// Cumulative receipts for one alias and mint, written only by the transfer instructions.
+/// PDA: [b"stats", alias, mint] - other programs can read it for reputation or credit scoring.
+#[account]
+pub struct PaymentStats {
+    pub alias_ref: Pubkey,
+    pub mint: Pubkey,              // NATIVE_SOL_MINT for SOL
+    pub total_volume: u128,        // In base units of the mint
+    pub payment_count: u64,
+    pub last_payment_at: i64,
+    pub unique_payers: u64,        // Approximate - undercounts as payer_filter fills up
+    pub payer_filter: [u8; 128],   // 1024-bit Bloom filter of payer keys
+    pub tracking_since: i64,       // Stats only cover payments from this timestamp on
+    pub bump: u8,
+    pub schema_version: u8,        // See VersionedAccount
+}
+
+impl PaymentStats {
+    pub fn record(&mut self, payer: &Pubkey, amount: u64, now: i64) -> Result<()> {
+        self.total_volume = self.total_volume.checked_add(amount as u128).ok_or(UnikError::Overflow)?;
+        self.payment_count = self.payment_count.checked_add(1).ok_or(UnikError::Overflow)?;
+        self.last_payment_at = now;
+
+        // Public keys are uniformly distributed, so their bytes can index the filter directly
+        let key = payer.to_bytes();
+        let mut is_new = false;
+        for bit in [u16::from_le_bytes([key[0], key[1]]), u16::from_le_bytes([key[2], key[3]])] {
+            let bit = (bit % 1024) as usize;
+            if self.payer_filter[bit / 8] & (1 << (bit % 8)) == 0 {
+                self.payer_filter[bit / 8] |= 1 << (bit % 8);
+                is_new = true;
+            }
+        }
+        if is_new {
+            self.unique_payers = self.unique_payers.checked_add(1).ok_or(UnikError::Overflow)?;
+        }
+        Ok(())
+    }
+}
+
+/// On-chain record of a single payment, linking later refunds to it.
+/// PDA: [b"receipt", reference] - only created when the payer passes a reference.
+#[account]
+pub struct Receipt {
+    pub alias_ref: Pubkey,
+    pub payer: Pubkey,
+    pub mint: Pubkey,              // NATIVE_SOL_MINT for SOL
+    pub amount: u64,
+    pub splits: Vec<Split>,        // Route at payment time - refunds are computed from this snapshot
+    pub refunded_amount: u64,
+    pub is_refunded: bool,         // True once refunded_amount == amount
+    pub reference: Pubkey,
+    pub created_at: i64,
+    pub bump: u8,
+    pub schema_version: u8,        // See VersionedAccount
+}
+
+/// Funds held for an alias until released into its route or refunded.
+/// PDA: [b"escrow", alias, payer, escrow_id]; token funds sit in [b"escrow_vault", escrow].
+#[account]
+pub struct Escrow {
+    pub alias_ref: Pubkey,
+    pub payer: Pubkey,
+    pub mint: Pubkey,              // NATIVE_SOL_MINT: lamports are held by this account
+    pub amount: u64,
+    pub escrow_id: u64,            // Payer-chosen, distinguishes concurrent escrows
+    pub arbiter: Option<Pubkey>,   // May release or refund to resolve a dispute
+    pub release_after: i64,        // Anyone may release from this timestamp on
+    pub created_at: i64,
+    pub bump: u8,
+    pub schema_version: u8,        // See VersionedAccount
+}
+
+/// Recurring token payment to an alias, pulled by `collect_subscription` via SPL delegation.
+/// PDA: [b"subscription", alias, payer, mint]
+#[account]
+pub struct Subscription {
+    pub payer: Pubkey,
+    pub alias_ref: Pubkey,
+    pub mint: Pubkey,
+    pub payer_token_account: Pubkey,
+    pub amount: u64,               // Charged once per period
+    pub period: i64,               // Seconds
+    pub next_due: i64,
+    pub allowance: u64,            // Left to charge: this subscription's share of the delegate approval
+    pub created_at: i64,
+    pub bump: u8,
+    pub schema_version: u8,        // See VersionedAccount
+}
+
+/// Tokens vesting linearly to an alias, held in [b"stream_vault", stream].
+/// PDA: [b"stream", alias, sender, stream_id]
+#[account]
+pub struct Stream {
+    pub sender: Pubkey,
+    pub alias_ref: Pubkey,
+    pub mint: Pubkey,
+    pub stream_id: u64,            // Sender-chosen, distinguishes concurrent streams
+    pub total_amount: u64,
+    pub withdrawn: u64,            // Already released through the route
+    pub start_ts: i64,
+    pub end_ts: i64,
+    pub bump: u8,
+    pub schema_version: u8,        // See VersionedAccount
+}
+
+impl Stream {
+    /// Amount vested at `now`, linear between start_ts and end_ts
+    pub fn vested_amount(&self, now: i64) -> Result<u64> {
+        if now <= self.start_ts {
+            return Ok(0);
+        }
+        if now >= self.end_ts {
+            return Ok(self.total_amount);
+        }
+        let elapsed = (now - self.start_ts) as u128;
+        let duration = (self.end_ts - self.start_ts) as u128;
+        Ok((self.total_amount as u128)
+            .checked_mul(elapsed)
+            .ok_or(UnikError::Overflow)?
+            .checked_div(duration)
+            .ok_or(UnikError::Overflow)? as u64)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct Split {
+    pub recipient: Pubkey,
+    pub percentage: u16, // Basis points (10000 = 100%)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct BatchEntry {
+    pub alias: String,
+    pub amount: u64,
+    pub referral_bps: u16,                        // 0 = no referrer
+    pub expected_route_hash: Option<[u8; 32]>,    // See `execute_transfer`
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PaymentQuote {
+    pub mint: Pubkey,          // Pubkey::default() = native SOL
+    pub amount: u64,
+    pub fee: u64,              // Charged on top of `amount`
+    pub legs: Vec<QuoteLeg>,   // In remaining_accounts order
+    pub referral: Option<QuoteLeg>, // Passed as `referrer` (SOL) or `referrer_token_account` (tokens)
+    pub route_hash: [u8; 32],  // Pass as expected_route_hash to pin these legs
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct QuoteLeg {
+    pub recipient: Pubkey,
+    pub account: Pubkey,       // Wallet for SOL, ATA for tokens
+    pub amount: u64,
+}
+
+#[error_code]
+pub enum UnikError {
+    #[msg("You are not the owner of this alias.")]
+    Unauthorized,
     #[msg("Split percentages exceed 100%.")]
     InvalidSplitTotal,
     #[msg("Arithmetic overflow.")]
@@ -606,7 +5344,7 @@ pub enum UnikError {
     AliasAlreadyInactive,
     #[msg("Alias is already active.")]
     AliasAlreadyActive,
-    #[msg("Amount too small. Minimum is 10000 units to prevent dust transactions.")]
+    #[msg("Amount is below the minimum payment accepted by this alias for this mint.")]
     AmountTooSmall,
     #[msg("The provided alias account address does not match the derived PDA.")]
     InvalidPDA,
@@ -614,21 +5352,1059 @@ pub enum UnikError {
     AliasInactive,
     #[msg("The provided recipient account does not match the split route.")]
     InvalidRecipientAccount,
+    #[msg("Signer is not a recipient in this alias route.")]
+    NotASplitRecipient,
+    #[msg("Amount exceeds the maximum payment accepted by this alias for this mint.")]
+    AmountTooLarge,
+    #[msg("Minimum must be greater than zero and not exceed the maximum.")]
+    InvalidPaymentLimits,
+    #[msg("Memo exceeds 128 characters.")]
+    MemoTooLong,
+    #[msg("A reference account is required to create a receipt.")]
+    MissingReference,
+    #[msg("A receipt already exists for this reference.")]
+    ReceiptAlreadyExists,
+    #[msg("Refund amount must be non-zero and not exceed the unrefunded payment.")]
+    InvalidRefundAmount,
+    #[msg("Every recipient returning funds must sign the refund.")]
+    RecipientMustSign,
+    #[msg("Escrow timeout must be positive.")]
+    InvalidEscrowTimeout,
+    #[msg("Only the payer can release this escrow before its timeout.")]
+    EscrowLocked,
+    #[msg("The escrow vault and token program are required for token escrows.")]
+    MissingEscrowVault,
+    #[msg("Subscription period must be positive.")]
+    InvalidSubscriptionPeriod,
+    #[msg("Allowance must cover at least one period.")]
+    InsufficientAllowance,
+    #[msg("Subscription payment is not due yet.")]
+    SubscriptionNotDue,
+    #[msg("A token account required for this instruction is missing.")]
+    MissingTokenAccount,
+    #[msg("Stream end must be after its start.")]
+    InvalidStreamSchedule,
+    #[msg("Nothing has vested since the last withdrawal.")]
+    NothingToWithdraw,
+    #[msg("The alias route has no splits to pay into.")]
+    RouteNotConfigured,
+    #[msg("Batch must contain at least one entry.")]
+    EmptyBatch,
+    #[msg("Exactly one delivery preference per split is required.")]
+    InvalidDeliveryPreferences,
+    #[msg("The token program is required to pay recipients preferring wrapped SOL.")]
+    MissingTokenProgram,
+    #[msg("The unwrap account is required to pay recipients preferring native SOL.")]
+    MissingUnwrapAccount,
+    #[msg("Recipients preferring native SOL can't be paid in a batch; use execute_token_transfer.")]
+    SolDeliveryUnsupported,
+    #[msg("The price feed account is not a valid, trading Pyth price account.")]
+    InvalidPriceFeed,
+    #[msg("Max staleness must be positive and max confidence at most 10000 bps.")]
+    InvalidPriceFeedConfig,
+    #[msg("The price feed has not been updated recently enough.")]
+    StalePrice,
+    #[msg("The price feed confidence interval is too wide.")]
+    PriceConfidenceTooWide,
+    #[msg("Converted amount exceeds the payer's maximum.")]
+    PriceSlippageExceeded,
+    #[msg("Currency must be a three-letter uppercase ISO 4217 code.")]
+    InvalidCurrency,
+    #[msg("Referral max bps must be 1-10000 and the allow list at most 10 referrers (or open).")]
+    InvalidReferralPolicy,
+    #[msg("This alias does not accept referrals.")]
+    ReferralsNotEnabled,
+    #[msg("The referrer account is required for a referral payment.")]
+    MissingReferrer,
+    #[msg("Requested referral share exceeds the alias's maximum.")]
+    ReferralFeeTooHigh,
+    #[msg("The referrer is not allowed by the alias's referral policy.")]
+    ReferrerNotAllowed,
+    #[msg("The route's splits changed since the payment was prepared.")]
+    RouteChanged,
+    #[msg("A split destination account must be writable.")]
+    RecipientNotWritable,
+    #[msg("The same destination account appears for more than one split.")]
+    DuplicateRecipientAccount,
+    #[msg("The account already uses the current layout.")]
+    AccountAlreadyMigrated,
+    #[msg("This operation is paused by the protocol.")]
+    ProtocolPaused,
+    #[msg("Unknown pause flag bits.")]
+    InvalidPauseFlags,
+    #[msg("This alias is frozen pending compliance review.")]
+    AliasFrozen,
+    #[msg("This alias is not frozen.")]
+    AliasNotFrozen,
+    #[msg("Freeze reason code must be non-zero.")]
+    InvalidFreezeReason,
+    #[msg("This alias does not accept payments in this mint.")]
+    MintNotAccepted,
+    #[msg("A mint filter holds at most 16 mints.")]
+    TooManyFilterMints,
+    #[msg("This alias is already tokenized.")]
+    AliasAlreadyTokenized,
+    #[msg("This alias is not tokenized.")]
+    AliasNotTokenized,
+    #[msg("This alias is tokenized - detokenize it first.")]
+    AliasTokenized,
+    #[msg("Alias NFTs can only move as a whole token through a Token-2022 transfer.")]
+    InvalidNftTransfer,
+    #[msg("Royalty can't exceed 10%.")]
+    InvalidRoyalty,
+    #[msg("Price must be greater than zero.")]
+    InvalidPrice,
+    #[msg("The listing changed since it was quoted.")]
+    ListingChanged,
+    #[msg("The offer changed since it was seen.")]
+    OfferChanged,
+    #[msg("The alias owner can't buy their own alias.")]
+    InvalidBuyer,
+    #[msg("The protocol treasury account is missing or wrong.")]
+    InvalidTreasury,
+    #[msg("This alias is up for auction.")]
+    AliasInAuction,
+    #[msg("This alias is already registered.")]
+    AliasAlreadyRegistered,
+    #[msg("Auction duration must be positive and the extension non-negative.")]
+    InvalidAuctionSchedule,
+    #[msg("The auction is not open for bids.")]
+    AuctionNotOpen,
+    #[msg("Bid is below the reserve price or the minimum increment.")]
+    BidTooLow,
+    #[msg("The auction has not ended yet.")]
+    AuctionNotEnded,
+    #[msg("An auction with bids can't be cancelled.")]
+    AuctionHasBids,
+    #[msg("Pass the alias account exactly when the auction has a winner.")]
+    InvalidAliasAccount,
+    #[msg("The alias this payment was made to has been deleted.")]
+    AliasGone,
+    #[msg("The alias has open escrows, streams, subscriptions or offers; settle them first.")]
+    AliasHasOpenObligations,
+}
+
+#[event]
+pub struct AliasRegisteredEvent {
+    pub alias: String,
+    pub owner: Pubkey,
+    pub metadata_uri: String,
+    pub version: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AliasMetadataUpdatedEvent {
+    pub alias: String,
+    pub owner: Pubkey,
+    pub old_metadata_uri: String,
+    pub new_metadata_uri: String,
+    pub version: u64,              // Version after the update
+    pub timestamp: i64,
 }
 
 #[event]
-pub struct AliasEvent {
-    pub event_type: String, // "REGISTER", "UPDATE", "DEACTIVATE", "REACTIVATE", "DELETE"
+pub struct AliasDeactivatedEvent {
     pub alias: String,
     pub owner: Pubkey,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct RouteEvent {
+pub struct AliasReactivatedEvent {
     pub alias: String,
-    pub splits_count: u8,
+    pub owner: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AliasDeletedEvent {
+    pub alias: String,
+    pub owner: Pubkey,
+    pub metadata_uri: String,      // Last state, so the history closes without reading the account
+    pub version: u64,
+    pub timestamp: i64,
+}
+
+/// Route events carry `RouteAccount::splits_hash` of the replaced splits, so a log consumer can
+/// check it against the splits of the previous RouteSetEvent and detect gaps in its history.
+#[event]
+pub struct RouteSetEvent {
+    pub alias: String,
+    pub splits: Vec<Split>,
+    pub previous_splits_hash: [u8; 32],   // Hash of the empty list for a fresh route
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RouteDeletedEvent {
+    pub alias: String,
+    pub previous_splits_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RouteMigratedEvent {
+    pub alias: String,
+    pub previous_splits_hash: Option<[u8; 32]>, // None if the stale data didn't hold a readable split list
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SplitConsentEvent {
+    pub alias: String,
+    pub recipient: Pubkey,
+    pub split_index: u8,
+    pub consented_mask: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentLimitsEvent {
+    pub alias: String,
+    pub mint: Pubkey,
+    pub min_amount: u64,
+    pub max_amount: Option<u64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentEvent {
+    pub alias: String,
+    pub payer: Pubkey,
+    pub mint: Pubkey,              // NATIVE_SOL_MINT for SOL
+    pub amount: u64,
+    pub reference: Option<Pubkey>,
+    pub memo: Option<String>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RefundEvent {
+    pub receipt: Pubkey,
+    pub payer: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub refunded_amount: u64,      // Cumulative, including this refund
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EscrowCreatedEvent {
+    pub escrow: Pubkey,
+    pub alias: String,
+    pub payer: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub arbiter: Option<Pubkey>,
+    pub release_after: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EscrowSettledEvent {
+    pub escrow: Pubkey,
+    pub alias: String,
+    pub payer: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub released: bool,            // false = refunded to the payer
+    pub settled_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SubscriptionEvent {
+    pub subscription: Pubkey,
+    pub alias: String,
+    pub payer: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub period: i64,
+    pub is_active: bool,           // false = cancelled
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SubscriptionChargedEvent {
+    pub subscription: Pubkey,
+    pub alias: String,
+    pub payer: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub next_due: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StreamCreatedEvent {
+    pub stream: Pubkey,
+    pub alias: String,
+    pub sender: Pubkey,
+    pub mint: Pubkey,
+    pub total_amount: u64,
+    pub start_ts: i64,
+    pub end_ts: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StreamWithdrawnEvent {
+    pub stream: Pubkey,
+    pub alias: String,
+    pub amount: u64,
+    pub withdrawn: u64,            // Cumulative, including this withdrawal
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StreamCancelledEvent {
+    pub stream: Pubkey,
+    pub alias: String,
+    pub released: u64,             // Vested remainder sent through the route
+    pub returned: u64,             // Unvested part returned to the sender
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FiatConversionEvent {
+    pub alias: String,
+    pub currency: [u8; 3],
+    pub fiat_amount: u64,          // Millionths of the currency unit
+    pub lamports: u64,
+    pub price: i64,
+    pub expo: i32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralPaidEvent {
+    pub alias: String,
+    pub payer: Pubkey,
+    pub referrer: Pubkey,
+    pub mint: Pubkey,              // NATIVE_SOL_MINT for SOL
+    pub amount: u64,
+    pub referral_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountMigratedEvent {
+    pub account: Pubkey,
+    pub schema_version: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ConfigAuthoritiesEvent {
+    pub authority: Pubkey,
+    pub pause_authority: Pubkey,
+    pub compliance_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MintFilterEvent {
+    pub alias: String,
+    pub mode: Option<MintFilterMode>, // None = filter deleted, every mint accepted
+    pub mints: Vec<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketplaceFeeEvent {
+    pub treasury: Pubkey,
+    pub royalty_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ListingEvent {
+    pub alias: String,
+    pub seller: Pubkey,
+    pub mint: Pubkey,
+    pub price: u64,
+    pub listed: bool,              // false = delisted
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OfferEvent {
+    pub alias: String,
+    pub buyer: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub open: bool,                // false = cancelled; acceptance emits AliasSoldEvent
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AliasSoldEvent {
+    pub alias: String,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub mint: Pubkey,
+    pub price: u64,
+    pub royalty: u64,              // Part of price paid to the protocol treasury
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuctionCreatedEvent {
+    pub alias: String,
+    pub reserve_price: u64,
+    pub start_ts: i64,
+    pub end_ts: i64,
+    pub extension: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BidEvent {
+    pub alias: String,
+    pub bidder: Pubkey,
+    pub amount: u64,
+    pub end_ts: i64,               // After any anti-sniping extension
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuctionSettledEvent {
+    pub alias: String,
+    pub winner: Option<Pubkey>,    // None = no bids or cancelled
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AliasTokenizeEvent {
+    pub alias: String,
+    pub mint: Pubkey,
+    pub holder: Pubkey,
+    pub tokenized: bool,           // false = detokenized, the mint is closed
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AliasTransferredEvent {
+    pub alias: String,
+    pub old_owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub version: u64,              // Version after the transfer
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AliasFreezeEvent {
+    pub alias: String,
+    pub frozen: bool,              // false = unfrozen
+    pub reason_code: u16,          // Reason the freeze was placed with
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PauseEvent {
+    pub authority: Pubkey,         // Signer that changed the flags
+    pub previous_flags: u8,
+    pub paused_flags: u8,
     pub timestamp: i64,
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(AnchorSerialize)]
+    struct AliasAccountV0 {
+        owner: Pubkey,
+        alias: String,
+        metadata_uri: String,
+        version: u64,
+        is_active: bool,
+        registered_at: i64,
+        bump: u8,
+    }
+
+    #[derive(AnchorSerialize)]
+    struct AliasAccountV1 {
+        v0: AliasAccountV0,
+        schema_version: u8,
+    }
+
+    #[derive(AnchorSerialize)]
+    struct AliasAccountV2 {
+        v1: AliasAccountV1,
+        freeze_reason: u16,
+        frozen_at: i64,
+        frozen_by: Pubkey,
+    }
+
+    #[derive(AnchorSerialize)]
+    struct AliasAccountV3 {
+        v2: AliasAccountV2,
+        nft_mint: Pubkey,
+    }
+
+    #[derive(AnchorSerialize)]
+    struct ConfigV1 {
+        authority: Pubkey,
+        pause_authority: Pubkey,
+        paused_flags: u8,
+        bump: u8,
+        schema_version: u8,
+    }
+
+    #[derive(AnchorSerialize)]
+    struct ConfigV2 {
+        v1: ConfigV1,
+        compliance_authority: Pubkey,
+    }
+
+    #[derive(AnchorSerialize)]
+    struct RouteAccountV0 {
+        alias_ref: Pubkey,
+        splits: Vec<Split>,
+        bump: u8,
+    }
+
+    #[derive(AnchorSerialize)]
+    struct RouteAccountV0Consent {
+        alias_ref: Pubkey,
+        splits: Vec<Split>,
+        bump: u8,
+        consented_mask: u8,
+    }
+
+    #[derive(AnchorSerialize)]
+    struct RouteAccountV0Delivery {
+        alias_ref: Pubkey,
+        splits: Vec<Split>,
+        bump: u8,
+        consented_mask: u8,
+        sol_delivery: Vec<SolDelivery>,
+    }
+
+    /// Account data as written by the program: discriminator, fields, zero padding up to `space`
+    fn account_data<T: Discriminator>(fields: &impl AnchorSerialize, space: usize) -> Vec<u8> {
+        let mut data = T::DISCRIMINATOR.to_vec();
+        data.extend(fields.try_to_vec().unwrap());
+        assert!(data.len() <= space);
+        data.resize(space, 0);
+        data
+    }
+
+    fn serialize(account: &impl AccountSerialize) -> Vec<u8> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    fn splits(count: usize) -> Vec<Split> {
+        (0..count)
+            .map(|_| Split { recipient: Pubkey::new_unique(), percentage: (10000 / count) as u16 })
+            .collect()
+    }
+
+    fn alias_v0(alias: &str, metadata_uri: &str) -> AliasAccountV0 {
+        AliasAccountV0 {
+            owner: Pubkey::new_unique(),
+            alias: alias.to_string(),
+            metadata_uri: metadata_uri.to_string(),
+            version: 3,
+            is_active: true,
+            registered_at: 1_700_000_000,
+            bump: 254,
+        }
+    }
+
+    const ALIAS_V0_SPACE: usize = 8 + 32 + 4 + 32 + 4 + 200 + 8 + 1 + 8 + 1;
+
+    fn assert_alias_upgraded(legacy: &AliasAccountV0, data: &[u8]) {
+        let account = upgrade_layout::<AliasAccount>(data).unwrap();
+        assert_eq!(account.owner, legacy.owner);
+        assert_eq!(account.alias, legacy.alias);
+        assert_eq!(account.metadata_uri, legacy.metadata_uri);
+        assert_eq!(account.version, legacy.version);
+        assert_eq!(account.is_active, legacy.is_active);
+        assert_eq!(account.registered_at, legacy.registered_at);
+        assert_eq!(account.bump, legacy.bump);
+        assert_eq!(account.schema_version, AliasAccount::SCHEMA_VERSION);
+        assert_eq!(account.freeze_reason, 0);
+        assert_eq!(account.frozen_at, 0);
+        assert_eq!(account.frozen_by, Pubkey::default());
+        assert_eq!(account.nft_mint, Pubkey::default());
+        assert_eq!(account.open_obligations, 0);
+    }
+
+    #[test]
+    fn upgrades_alias_v0_with_padding() {
+        let legacy = alias_v0("shop", "https://example.com/metadata");
+        assert_alias_upgraded(&legacy, &account_data::<AliasAccount>(&legacy, ALIAS_V0_SPACE));
+    }
+
+    #[test]
+    fn upgrades_alias_v0_at_full_length() {
+        let legacy = alias_v0(&"a".repeat(32), &"u".repeat(200));
+        let data = account_data::<AliasAccount>(&legacy, ALIAS_V0_SPACE);
+        // No padding left: the current layout doesn't fit until the account grows
+        assert!(AliasAccount::try_deserialize(&mut &data[..]).is_err());
+        assert_alias_upgraded(&legacy, &data);
+    }
+
+    #[test]
+    fn upgrades_alias_v1() {
+        let legacy = AliasAccountV1 { v0: alias_v0(&"b".repeat(32), &"u".repeat(200)), schema_version: 1 };
+        assert_alias_upgraded(&legacy.v0, &account_data::<AliasAccount>(&legacy, ALIAS_V0_SPACE + 1));
+    }
+
+    fn frozen_alias_v2() -> AliasAccountV2 {
+        AliasAccountV2 {
+            v1: AliasAccountV1 { v0: alias_v0(&"c".repeat(32), &"u".repeat(200)), schema_version: 2 },
+            freeze_reason: 7,
+            frozen_at: 1_700_000_100,
+            frozen_by: Pubkey::new_unique(),
+        }
+    }
+
+    fn assert_freeze_kept(account: &AliasAccount, legacy: &AliasAccountV2) {
+        assert_eq!(account.freeze_reason, legacy.freeze_reason);
+        assert_eq!(account.frozen_at, legacy.frozen_at);
+        assert_eq!(account.frozen_by, legacy.frozen_by);
+    }
+
+    #[test]
+    fn upgrades_alias_v2() {
+        let legacy = frozen_alias_v2();
+        let account = upgrade_layout::<AliasAccount>(&account_data::<AliasAccount>(&legacy, ALIAS_V0_SPACE + 1 + 2 + 8 + 32)).unwrap();
+        assert_eq!(account.owner, legacy.v1.v0.owner);
+        assert_eq!(account.alias, legacy.v1.v0.alias);
+        assert_eq!(account.metadata_uri, legacy.v1.v0.metadata_uri);
+        assert_eq!(account.registered_at, legacy.v1.v0.registered_at);
+        assert_freeze_kept(&account, &legacy);
+        assert_eq!(account.nft_mint, Pubkey::default());
+        assert_eq!(account.open_obligations, 0);
+        assert_eq!(account.schema_version, AliasAccount::SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn upgrades_alias_v3() {
+        let mut v2 = frozen_alias_v2();
+        v2.v1.schema_version = 3;
+        let legacy = AliasAccountV3 { v2, nft_mint: Pubkey::new_unique() };
+        let data = account_data::<AliasAccount>(&legacy, ALIAS_V0_SPACE + 1 + 2 + 8 + 32 + 32);
+        let account = upgrade_layout::<AliasAccount>(&data).unwrap();
+        assert_eq!(account.owner, legacy.v2.v1.v0.owner);
+        assert_eq!(account.alias, legacy.v2.v1.v0.alias);
+        assert_freeze_kept(&account, &legacy.v2);
+        assert_eq!(account.nft_mint, legacy.nft_mint);
+        assert_eq!(account.open_obligations, 0);
+        assert_eq!(account.schema_version, AliasAccount::SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn escrow_follows_the_alias_registration() {
+        let legacy = alias_v0("shop", "");
+        let mut escrow = Escrow {
+            alias_ref: Pubkey::new_unique(),
+            payer: Pubkey::new_unique(),
+            mint: NATIVE_SOL_MINT,
+            amount: 1_000_000,
+            escrow_id: 0,
+            arbiter: None,
+            release_after: legacy.registered_at + 60,
+            created_at: legacy.registered_at,
+            bump: 255,
+            schema_version: Escrow::SCHEMA_VERSION,
+        };
+        let (key, owner, mut lamports) = (Pubkey::new_unique(), crate::ID, 0);
+        let mut data = account_data::<AliasAccount>(&legacy, ALIAS_V0_SPACE);
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert_eq!(escrow_alias(&info, &escrow).unwrap().unwrap().owner, legacy.owner);
+
+        // Registered again after the escrow was made
+        escrow.created_at -= 1;
+        assert!(escrow_alias(&info, &escrow).unwrap().is_none());
+
+        let (system, mut lamports, mut data) = (system_program::ID, 0, Vec::new());
+        let deleted = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &system, false, 0);
+        assert!(escrow_alias(&deleted, &escrow).unwrap().is_none());
+    }
+
+    #[test]
+    fn mint_filter_modes() {
+        let (listed, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut filter = MintFilter {
+            alias_ref: Pubkey::new_unique(),
+            mode: MintFilterMode::Allow,
+            mints: vec![listed],
+            bump: 255,
+            schema_version: MintFilter::SCHEMA_VERSION,
+        };
+        assert!(filter.accepts(&listed));
+        assert!(!filter.accepts(&other));
+        filter.mode = MintFilterMode::Deny;
+        assert!(!filter.accepts(&listed));
+        assert!(filter.accepts(&other));
+        assert!(serialize(&filter).len() <= MintFilter::SPACE - 32 * (MAX_FILTER_MINTS - 1));
+    }
+
+    #[test]
+    fn upgrades_config_v1() {
+        let legacy = ConfigV1 {
+            authority: Pubkey::new_unique(),
+            pause_authority: Pubkey::new_unique(),
+            paused_flags: PAUSE_SOL_TRANSFERS,
+            bump: 253,
+            schema_version: 1,
+        };
+        let config = upgrade_layout::<Config>(&account_data::<Config>(&legacy, 8 + 32 + 32 + 1 + 1 + 1)).unwrap();
+        assert_eq!(config.authority, legacy.authority);
+        assert_eq!(config.pause_authority, legacy.pause_authority);
+        assert_eq!(config.paused_flags, PAUSE_SOL_TRANSFERS);
+        assert_eq!(config.bump, 253);
+        assert_eq!(config.schema_version, Config::SCHEMA_VERSION);
+        assert_eq!(config.compliance_authority, Pubkey::default());
+        assert_eq!(config.treasury, Pubkey::default());
+        assert_eq!(config.royalty_bps, 0);
+    }
+
+    #[test]
+    fn upgrades_config_v2() {
+        let legacy = ConfigV2 {
+            v1: ConfigV1 {
+                authority: Pubkey::new_unique(),
+                pause_authority: Pubkey::new_unique(),
+                paused_flags: PAUSE_MARKETPLACE,
+                bump: 252,
+                schema_version: 2,
+            },
+            compliance_authority: Pubkey::new_unique(),
+        };
+        let config = upgrade_layout::<Config>(&account_data::<Config>(&legacy, 8 + 32 + 32 + 1 + 1 + 1 + 32)).unwrap();
+        assert_eq!(config.authority, legacy.v1.authority);
+        assert_eq!(config.pause_authority, legacy.v1.pause_authority);
+        assert_eq!(config.paused_flags, PAUSE_MARKETPLACE);
+        assert_eq!(config.bump, 252);
+        assert_eq!(config.compliance_authority, legacy.compliance_authority);
+        assert_eq!(config.treasury, Pubkey::default());
+        assert_eq!(config.royalty_bps, 0);
+        assert_eq!(config.schema_version, Config::SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn auction_min_bid() {
+        let mut auction = Auction {
+            alias: "premium".to_string(),
+            creator: Pubkey::new_unique(),
+            reserve_price: 1_000,
+            start_ts: 0,
+            end_ts: 100,
+            extension: 10,
+            highest_bidder: Pubkey::default(),
+            highest_bid: 0,
+            bump: 255,
+            schema_version: Auction::SCHEMA_VERSION,
+        };
+        assert_eq!(auction.min_bid().unwrap(), 1_000);
+        auction.highest_bidder = Pubkey::new_unique();
+        auction.highest_bid = 1_000;
+        assert_eq!(auction.min_bid().unwrap(), 1_050);
+        auction.highest_bid = 10;
+        assert_eq!(auction.min_bid().unwrap(), 1_000);
+        auction.reserve_price = 1;
+        assert_eq!(auction.min_bid().unwrap(), 11);
+        auction.highest_bid = u64::MAX;
+        assert!(auction.min_bid().is_err());
+    }
+
+    #[test]
+    fn royalty_needs_treasury_and_rounds_down() {
+        let mut config = Config {
+            authority: Pubkey::new_unique(),
+            pause_authority: Pubkey::new_unique(),
+            paused_flags: 0,
+            bump: 255,
+            schema_version: Config::SCHEMA_VERSION,
+            compliance_authority: Pubkey::default(),
+            treasury: Pubkey::default(),
+            royalty_bps: 250,
+        };
+        assert_eq!(config.royalty(1_000_000).unwrap(), 0);
+        config.treasury = Pubkey::new_unique();
+        assert_eq!(config.royalty(1_000_000).unwrap(), 25_000);
+        assert_eq!(config.royalty(39).unwrap(), 0);
+        assert_eq!(config.royalty(u64::MAX).unwrap(), u64::MAX / 10000 * 250 + (u64::MAX % 10000) * 250 / 10000);
+    }
+
+    #[test]
+    fn upgrades_route_v0_layouts() {
+        let alias_ref = Pubkey::new_unique();
+        let splits = splits(5);
+        let datas = [
+            account_data::<RouteAccount>(
+                &RouteAccountV0 { alias_ref, splits: splits.clone(), bump: 255 },
+                8 + 32 + 4 + (5 * 34) + 1 + 100,
+            ),
+            account_data::<RouteAccount>(
+                &RouteAccountV0Consent { alias_ref, splits: splits.clone(), bump: 255, consented_mask: 0b101 },
+                8 + 32 + 4 + (5 * 34) + 1 + 1 + 100,
+            ),
+            account_data::<RouteAccount>(
+                &RouteAccountV0Delivery {
+                    alias_ref,
+                    splits: splits.clone(),
+                    bump: 255,
+                    consented_mask: 0b101,
+                    sol_delivery: vec![SolDelivery::Native, SolDelivery::Wrapped],
+                },
+                8 + 32 + 4 + (5 * 34) + 1 + 1 + 4 + 5 + 100,
+            ),
+        ];
+
+        for (layout, data) in datas.iter().enumerate() {
+            let account = upgrade_layout::<RouteAccount>(data).unwrap();
+            assert_eq!(account.alias_ref, alias_ref);
+            assert_eq!(account.splits_hash().unwrap(), hash(&splits.try_to_vec().unwrap()).to_bytes());
+            assert_eq!(account.bump, 255);
+            assert_eq!(account.consented_mask, if layout == 0 { 0 } else { 0b101 });
+            assert_eq!(account.sol_delivery_for(1), if layout == 2 { SolDelivery::Wrapped } else { SolDelivery::AsPaid });
+            assert_eq!(account.schema_version, RouteAccount::SCHEMA_VERSION);
+
+            // Stale layouts still yield the hash the route events chain on
+            let (key, owner, mut lamports, mut data) = (Pubkey::new_unique(), crate::ID, 0, data.clone());
+            let info = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &owner, false, 0);
+            assert_eq!(stored_splits_hash(&info), Some(account.splits_hash().unwrap()));
+        }
+    }
+
+    /// Fixed-size accounts: v0 is the current layout without the trailing schema_version byte
+    fn assert_upgrades_unversioned<T: VersionedAccount>(mut account: T) {
+        account.set_schema_version(0);
+        let mut legacy = serialize(&account);
+        legacy.pop();
+
+        let upgraded = upgrade_layout::<T>(&legacy).unwrap();
+        account.set_schema_version(T::SCHEMA_VERSION);
+        assert_eq!(serialize(&upgraded), serialize(&account));
+    }
+
+    #[test]
+    fn upgrades_unversioned_fixed_size_accounts() {
+        let key = Pubkey::new_unique;
+        assert_upgrades_unversioned(PaymentLimits {
+            alias_ref: key(),
+            mint: NATIVE_SOL_MINT,
+            min_amount: 10_000,
+            max_amount: Some(1_000_000),
+            bump: 250,
+            schema_version: 0,
+        });
+        assert_upgrades_unversioned(ReferralPolicy {
+            alias_ref: key(),
+            max_bps: 500,
+            open: false,
+            allowed_referrers: vec![key(), key()],
+            bump: 250,
+            schema_version: 0,
+        });
+        assert_upgrades_unversioned(FiatPriceFeed {
+            alias_ref: key(),
+            currency: *b"USD",
+            price_feed: key(),
+            max_staleness: 60,
+            max_confidence_bps: 100,
+            bump: 250,
+            schema_version: 0,
+        });
+        assert_upgrades_unversioned(PaymentStats {
+            alias_ref: key(),
+            mint: key(),
+            total_volume: 42,
+            payment_count: 2,
+            last_payment_at: 1_700_000_000,
+            unique_payers: 1,
+            payer_filter: [7; 128],
+            tracking_since: 1_600_000_000,
+            bump: 250,
+            schema_version: 0,
+        });
+        assert_upgrades_unversioned(Receipt {
+            alias_ref: key(),
+            payer: key(),
+            mint: NATIVE_SOL_MINT,
+            amount: 1_000,
+            splits: splits(2),
+            refunded_amount: 100,
+            is_refunded: false,
+            reference: key(),
+            created_at: 1_700_000_000,
+            bump: 250,
+            schema_version: 0,
+        });
+        assert_upgrades_unversioned(Escrow {
+            alias_ref: key(),
+            payer: key(),
+            mint: key(),
+            amount: 1_000,
+            escrow_id: 9,
+            arbiter: Some(key()),
+            release_after: 1_700_000_000,
+            created_at: 1_600_000_000,
+            bump: 250,
+            schema_version: 0,
+        });
+        assert_upgrades_unversioned(Subscription {
+            payer: key(),
+            alias_ref: key(),
+            mint: key(),
+            payer_token_account: key(),
+            amount: 1_000,
+            period: 86_400,
+            next_due: 1_700_000_000,
+            allowance: 12_000,
+            created_at: 1_600_000_000,
+            bump: 250,
+            schema_version: 0,
+        });
+        assert_upgrades_unversioned(Stream {
+            sender: key(),
+            alias_ref: key(),
+            mint: key(),
+            stream_id: 3,
+            total_amount: 1_000,
+            withdrawn: 250,
+            start_ts: 1_600_000_000,
+            end_ts: 1_700_000_000,
+            bump: 250,
+            schema_version: 0,
+        });
+    }
+
+    #[test]
+    fn rejects_current_layout() {
+        let limits = PaymentLimits {
+            alias_ref: Pubkey::new_unique(),
+            mint: NATIVE_SOL_MINT,
+            min_amount: 1,
+            max_amount: None,
+            bump: 250,
+            schema_version: PaymentLimits::SCHEMA_VERSION,
+        };
+        assert_eq!(
+            upgrade_layout::<PaymentLimits>(&serialize(&limits)).err().unwrap(),
+            UnikError::AccountAlreadyMigrated.into()
+        );
+    }
+
+    #[test]
+    fn rejects_other_account_types() {
+        let legacy = alias_v0("shop", "");
+        let data = account_data::<AliasAccount>(&legacy, ALIAS_V0_SPACE);
+        assert!(upgrade_layout::<RouteAccount>(&data).is_err());
+    }
+
+    /// A Pyth v2 price account holding only the fields `read_pyth_price` reads
+    fn pyth_price_data(price: i64, conf: u64, expo: i32, publish_time: i64) -> Vec<u8> {
+        let mut data = vec![0u8; 240];
+        data[0..4].copy_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+        data[4..8].copy_from_slice(&2u32.to_le_bytes());
+        data[8..12].copy_from_slice(&3u32.to_le_bytes());
+        data[20..24].copy_from_slice(&expo.to_le_bytes());
+        data[96..104].copy_from_slice(&publish_time.to_le_bytes());
+        data[208..216].copy_from_slice(&price.to_le_bytes());
+        data[216..224].copy_from_slice(&conf.to_le_bytes());
+        data[224..228].copy_from_slice(&1u32.to_le_bytes());
+        data
+    }
+
+    fn assert_error<T>(result: Result<T>, error: UnikError) {
+        match result {
+            Ok(_) => panic!("expected {error:?}"),
+            Err(err) => assert_eq!(err, error.into()),
+        }
+    }
+
+    #[test]
+    fn reads_pyth_price() {
+        let price = read_pyth_price(&pyth_price_data(15_000_000_000, 1_000_000, -8, 1_700_000_000)).unwrap();
+        assert_eq!((price.price, price.conf, price.expo, price.publish_time), (15_000_000_000, 1_000_000, -8, 1_700_000_000));
+
+        assert_error(read_pyth_price(&pyth_price_data(15_000_000_000, 0, -8, 0)[..239]), UnikError::InvalidPriceFeed);
+        let mut not_trading = pyth_price_data(15_000_000_000, 0, -8, 0);
+        not_trading[224] = 2;
+        assert_error(read_pyth_price(&not_trading), UnikError::InvalidPriceFeed);
+        let mut not_pyth = pyth_price_data(15_000_000_000, 0, -8, 0);
+        not_pyth[0] = 0;
+        assert_error(read_pyth_price(&not_pyth), UnikError::InvalidPriceFeed);
+
+        assert_error(read_pyth_price(&pyth_price_data(0, 0, -8, 0)), UnikError::InvalidPriceFeed);
+        assert_error(read_pyth_price(&pyth_price_data(-15_000_000_000, 0, -8, 0)), UnikError::InvalidPriceFeed);
+    }
+
+    #[test]
+    fn pyth_exponent_range() {
+        // The extremes convert any amount without overflowing the intermediate u128
+        for expo in [-12, 12] {
+            let price = read_pyth_price(&pyth_price_data(i64::MAX, 0, expo, 0)).unwrap();
+            assert!(fiat_to_lamports(u64::MAX, &price).is_ok());
+        }
+        assert_error(read_pyth_price(&pyth_price_data(1, 0, -13, 0)), UnikError::InvalidPriceFeed);
+        assert_error(read_pyth_price(&pyth_price_data(1, 0, 13, 0)), UnikError::InvalidPriceFeed);
+    }
+
+    #[test]
+    fn converts_fiat_to_lamports() {
+        let price = |price, expo| OraclePrice { price, conf: 0, expo, publish_time: 0 };
+        // 1 USD at 150 USD/SOL, with the price scaled down, unscaled and scaled up
+        assert_eq!(fiat_to_lamports(1_000_000, &price(15_000_000_000, -8)).unwrap(), 6_666_666);
+        assert_eq!(fiat_to_lamports(1_000_000, &price(150, 0)).unwrap(), 6_666_666);
+        assert_eq!(fiat_to_lamports(1_000_000, &price(15, 1)).unwrap(), 6_666_666);
+        // 1 USD at 10000 USD/SOL
+        assert_eq!(fiat_to_lamports(1_000_000, &price(1, 4)).unwrap(), 100_000);
+        // Too large to express in lamports
+        assert_error(fiat_to_lamports(u64::MAX, &price(1, -12)), UnikError::Overflow);
+    }
+
+    #[test]
+    fn rejects_stale_or_uncertain_prices() {
+        let feed = FiatPriceFeed {
+            alias_ref: Pubkey::new_unique(),
+            currency: *b"USD",
+            price_feed: Pubkey::new_unique(),
+            max_staleness: 60,
+            max_confidence_bps: 100,
+            bump: 255,
+            schema_version: FiatPriceFeed::SCHEMA_VERSION,
+        };
+        let price = OraclePrice { price: 15_000_000_000, conf: 150_000_000, expo: -8, publish_time: 1_700_000_000 };
+        require_usable_price(&price, &feed, 1_700_000_060).unwrap();
+        assert_error(require_usable_price(&price, &feed, 1_700_000_061), UnikError::StalePrice);
 
+        // Confidence exactly at 1% passes, one unit wider fails
+        let wide = OraclePrice { conf: 150_000_001, ..price };
+        assert_error(require_usable_price(&wide, &feed, 1_700_000_000), UnikError::PriceConfidenceTooWide);
+    }
+
+    #[test]
+    fn stream_vests_linearly() {
+        let stream = Stream {
+            sender: Pubkey::new_unique(),
+            alias_ref: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            stream_id: 0,
+            total_amount: 1_000,
+            withdrawn: 0,
+            start_ts: 1_000,
+            end_ts: 1_300,
+            bump: 255,
+            schema_version: Stream::SCHEMA_VERSION,
+        };
+        assert_eq!(stream.vested_amount(0).unwrap(), 0);
+        assert_eq!(stream.vested_amount(1_000).unwrap(), 0);
+        assert_eq!(stream.vested_amount(1_001).unwrap(), 3);
+        assert_eq!(stream.vested_amount(1_150).unwrap(), 500);
+        assert_eq!(stream.vested_amount(1_300).unwrap(), 1_000);
+        assert_eq!(stream.vested_amount(i64::MAX).unwrap(), 1_000);
+    }
+}
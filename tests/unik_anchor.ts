@@ -2,6 +2,20 @@ import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { UnikAnchor } from "../target/types/unik_anchor";
 import { assert } from "chai";
+import { createHash } from "crypto";
+import {
+  NATIVE_MINT,
+  TOKEN_2022_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  createAssociatedTokenAccount,
+  createAssociatedTokenAccountIdempotentInstruction,
+  createMint,
+  createTransferCheckedWithTransferHookInstruction,
+  createWrappedNativeAccount,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  mintTo,
+} from "@solana/spl-token";
 
 describe("unik_anchor", () => {
   // Configure the client to use the local cluster.
@@ -24,6 +38,90 @@ describe("unik_anchor", () => {
     program.programId
   );
 
+  // Run `send` and assert it fails with the program error `code`; returns the error for further checks
+  const expectError = async (send: () => Promise<unknown>, code: string): Promise<anchor.AnchorError> => {
+    try {
+      await send();
+    } catch (e: any) {
+      const error =
+        e instanceof anchor.AnchorError ? e : anchor.AnchorError.parse(e.logs ?? e.simulationResponse?.logs ?? []);
+      assert.equal(error?.error.errorCode.code, code, e.toString());
+      return error;
+    }
+    assert.fail(`Expected ${code}`);
+  };
+
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  // Fresh 6-decimal mint with `amount` minted to the wallet's ATA
+  const createFundedMint = async (amount: number) => {
+    const mint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    const ata = await createAssociatedTokenAccount(provider.connection, payer, mint, payer.publicKey);
+    await mintTo(provider.connection, payer, mint, ata, payer, amount);
+    return { mint, ata };
+  };
+
+  // Route `routeAlias` to new recipients with these percentages; returns their ATAs for `mint`, in split order
+  const routeToNewRecipients = async (routeAlias: string, mint: anchor.web3.PublicKey, percentages: number[]) => {
+    const recipients = percentages.map(() => anchor.web3.Keypair.generate().publicKey);
+    await program.methods
+      .setRouteConfig(routeAlias, recipients.map((recipient, i) => ({ recipient, percentage: percentages[i] })))
+      .accounts({ user: provider.wallet.publicKey })
+      .rpc();
+    return Promise.all(recipients.map((recipient) => createAssociatedTokenAccount(provider.connection, payer, mint, recipient)));
+  };
+
+  const tokenBalance = async (account: anchor.web3.PublicKey) =>
+    Number((await getAccount(provider.connection, account)).amount);
+
+  // Unix time of the validator clock, which the program's timestamps come from
+  const clusterTime = async () => provider.connection.getBlockTime(await provider.connection.getSlot());
+
+  const eventParser = new anchor.EventParser(program.programId, new anchor.BorshCoder(program.idl));
+  // Events emitted by a transaction sent with `rpc({ commitment: "confirmed" })`
+  const eventsOf = async (signature: string) => {
+    const tx = await provider.connection.getTransaction(signature, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+    return [...eventParser.parseLogs(tx.meta.logMessages)];
+  };
+  const eventOf = async (signature: string, name: string) => {
+    const event = (await eventsOf(signature)).find((e) => e.name === name);
+    assert.ok(event, `Expected ${name}`);
+    return event.data as any;
+  };
+
+  // The program PDA at [prefix, ...seeds]
+  const pda = (prefix: string, ...seeds: (string | anchor.web3.PublicKey)[]) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from(prefix), ...seeds.map((seed) => (typeof seed === "string" ? Buffer.from(seed) : seed.toBuffer()))],
+      program.programId
+    )[0];
+
+  const meta = (pubkey: anchor.web3.PublicKey, isWritable = false) => ({ pubkey, isWritable, isSigner: false });
+
+  // remaining_accounts of one batch_execute_token_transfer entry
+  const batchEntryAccounts = (
+    entryAlias: string,
+    mint: anchor.web3.PublicKey,
+    recipientAtas: anchor.web3.PublicKey[],
+    referrer?: anchor.web3.PublicKey
+  ) => [
+    meta(pda("alias", entryAlias)),
+    meta(pda("route", entryAlias)),
+    meta(pda("limits", entryAlias, mint)),
+    meta(pda("stats", entryAlias, mint), true),
+    meta(pda("mints", entryAlias)),
+    ...(referrer
+      ? [meta(pda("referral", entryAlias)), meta(referrer), meta(getAssociatedTokenAddressSync(mint, referrer), true)]
+      : []),
+    ...recipientAtas.map((ata) => meta(ata, true)),
+  ];
+
+  const streamPda = (streamAlias: string, streamId: anchor.BN) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("stream"), Buffer.from(streamAlias), provider.wallet.publicKey.toBuffer(), streamId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
   it("Registers an alias", async () => {
     await program.methods
       .registerAlias(alias, metadataUri)
@@ -93,9 +191,13 @@ describe("unik_anchor", () => {
     const initialBal2 = await provider.connection.getBalance(recipient2.publicKey);
 
     await program.methods
-      .executeTransfer(alias, amount)
+      .executeTransfer(alias, amount, "order-1", 0, null)
       .accounts({
         user: provider.wallet.publicKey,
+        reference: anchor.web3.Keypair.generate().publicKey,
+        receipt: null,
+        referralPolicy: null,
+        referrer: null,
       })
       .remainingAccounts([
         { pubkey: recipient1.publicKey, isWritable: true, isSigner: false },
@@ -111,6 +213,714 @@ describe("unik_anchor", () => {
     assert.equal(finalBal2 - initialBal2, 5000000);
   });
 
+  it("Records payment stats", async () => {
+    const sol = anchor.web3.PublicKey.default;
+    const [statsPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("stats"), Buffer.from(alias), sol.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .initPaymentStats(alias, sol)
+      .accounts({
+        user: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const route = await program.account.routeAccount.fetch(routePda);
+    await program.methods
+      .executeTransfer(alias, new anchor.BN(10000000), null, 0, null)
+      .accounts({
+        user: provider.wallet.publicKey,
+        reference: null,
+        receipt: null,
+        referralPolicy: null,
+        referrer: null,
+      })
+      .remainingAccounts(route.splits.map((s: any) => ({ pubkey: s.recipient, isWritable: true, isSigner: false })))
+      .rpc();
+
+    const stats = await program.account.paymentStats.fetch(statsPda);
+    assert.equal(stats.paymentCount.toNumber(), 1);
+    assert.equal(stats.totalVolume.toString(), "10000000");
+    assert.equal(stats.uniquePayers.toNumber(), 1);
+  });
+
+  it("Refunds a receipted payment", async () => {
+    const recipient = anchor.web3.Keypair.generate();
+    await program.methods
+      .setRouteConfig(alias, [{ recipient: recipient.publicKey, percentage: 10000 }])
+      .accounts({
+        user: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const reference = anchor.web3.Keypair.generate().publicKey;
+    const [receiptPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt"), reference.toBuffer()],
+      program.programId
+    );
+
+    const pay = () =>
+      program.methods
+        .executeTransfer(alias, new anchor.BN(10000000), null, 0, null)
+        .accounts({
+          user: provider.wallet.publicKey,
+          reference,
+          receipt: receiptPda,
+          referralPolicy: null,
+          referrer: null,
+        })
+        .remainingAccounts([{ pubkey: recipient.publicKey, isWritable: true, isSigner: false }])
+        .rpc();
+    const refund = (amount: number) =>
+      program.methods
+        .refundPayment(new anchor.BN(amount))
+        .accounts({
+          receipt: receiptPda,
+          payer: provider.wallet.publicKey,
+          payerTokenAccount: null,
+          tokenProgram: null,
+        })
+        .remainingAccounts([{ pubkey: recipient.publicKey, isWritable: true, isSigner: true }])
+        .signers([recipient])
+        .rpc();
+
+    // Lamports sent to the receipt address ahead of time don't block the payment
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({ fromPubkey: provider.wallet.publicKey, toPubkey: receiptPda, lamports: 1000000 })
+      )
+    );
+    await pay();
+    // A reference can be receipted only once
+    await expectError(pay, "ReceiptAlreadyExists");
+
+    await refund(4000000);
+    const receipt = await program.account.receipt.fetch(receiptPda);
+    assert.equal(receipt.refundedAmount.toNumber(), 4000000);
+    assert.equal(receipt.isRefunded, false);
+    assert.equal(await provider.connection.getBalance(recipient.publicKey), 6000000);
+
+    // Refunds can't exceed what is left of the payment
+    await expectError(() => refund(6000001), "InvalidRefundAmount");
+    await expectError(() => refund(0), "InvalidRefundAmount");
+    assert.equal(await provider.connection.getBalance(recipient.publicKey), 6000000);
+  });
+
+  it("Pays a referrer before the splits", async () => {
+    const recipient = anchor.web3.Keypair.generate();
+    const referrer = anchor.web3.Keypair.generate();
+    await program.methods
+      .setRouteConfig(alias, [{ recipient: recipient.publicKey, percentage: 10000 }])
+      .accounts({
+        user: provider.wallet.publicKey,
+      })
+      .rpc();
+    await program.methods
+      .setReferralPolicy(alias, 500, true, [])
+      .accounts({
+        user: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    // The quote runs the same referral check and carve-out as the payment
+    const quote = await program.methods
+      .quotePayment(alias, new anchor.BN(20000000), anchor.web3.PublicKey.default, 500, provider.wallet.publicKey)
+      .accounts({ tokenMint: null, referrer: referrer.publicKey })
+      .view();
+    assert.ok(quote.referral.account.equals(referrer.publicKey));
+    await expectError(
+      () =>
+        program.methods
+          .quotePayment(alias, new anchor.BN(20000000), anchor.web3.PublicKey.default, 500, referrer.publicKey)
+          .accounts({ tokenMint: null, referrer: referrer.publicKey })
+          .view(),
+      "ReferrerNotAllowed"
+    );
+
+    await program.methods
+      .executeTransfer(alias, new anchor.BN(20000000), null, 500, quote.routeHash)
+      .accounts({
+        user: provider.wallet.publicKey,
+        reference: null,
+        receipt: null,
+        referrer: quote.referral.account,
+      })
+      .remainingAccounts(quote.legs.map((leg: any) => meta(leg.account, true)))
+      .rpc();
+
+    assert.equal(await provider.connection.getBalance(referrer.publicKey), quote.referral.amount.toNumber());
+    assert.equal(await provider.connection.getBalance(recipient.publicKey), quote.legs[0].amount.toNumber());
+    assert.equal(await provider.connection.getBalance(referrer.publicKey), 1000000);
+    assert.equal(await provider.connection.getBalance(recipient.publicKey), 19000000);
+
+    await program.methods
+      .deleteReferralPolicy(alias)
+      .accounts({
+        user: provider.wallet.publicKey,
+      })
+      .rpc();
+  });
+
+  it("Escrows and releases a payment", async () => {
+    const route = await program.account.routeAccount.fetch(routePda);
+    const escrowId = new anchor.BN(1);
+    const [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), Buffer.from(alias), provider.wallet.publicKey.toBuffer(), escrowId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+
+    await program.methods
+      .createEscrow(alias, escrowId, new anchor.BN(5000000), new anchor.BN(3600), null)
+      .accounts({
+        user: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const recipient = route.splits[0].recipient;
+    const before = await provider.connection.getBalance(recipient);
+
+    await program.methods
+      .releaseEscrow(alias)
+      .accounts({
+        escrow: escrowPda,
+        payer: provider.wallet.publicKey,
+        vault: null,
+        payerTokenAccount: null,
+        tokenProgram: null,
+        authority: provider.wallet.publicKey,
+      })
+      .remainingAccounts([{ pubkey: recipient, isWritable: true, isSigner: false }])
+      .rpc();
+
+    assert.equal(await provider.connection.getBalance(recipient) - before, 5000000);
+    assert.isNull(await provider.connection.getAccountInfo(escrowPda));
+  });
+
+  it("Fail: Release an escrow into an empty route", async () => {
+    const unrouted = `${alias}_e`;
+    const escrowId = new anchor.BN(1);
+    const [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), Buffer.from(unrouted), provider.wallet.publicKey.toBuffer(), escrowId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods.registerAlias(unrouted, metadataUri).accounts({ user: provider.wallet.publicKey }).rpc();
+    await program.methods.initRouteConfig(unrouted).accounts({ user: provider.wallet.publicKey }).rpc();
+    await program.methods
+      .createEscrow(unrouted, escrowId, new anchor.BN(5000000), new anchor.BN(3600), null)
+      .accounts({ user: provider.wallet.publicKey })
+      .rpc();
+
+    const settleAccounts = {
+      escrow: escrowPda,
+      payer: provider.wallet.publicKey,
+      vault: null,
+      payerTokenAccount: null,
+      tokenProgram: null,
+      authority: provider.wallet.publicKey,
+    };
+    await expectError(
+      () => program.methods.releaseEscrow(unrouted).accounts(settleAccounts).rpc(),
+      "RouteNotConfigured"
+    );
+
+    // The funds stay in escrow until the alias owner refunds them
+    assert.equal((await program.account.escrow.fetch(escrowPda)).amount.toNumber(), 5000000);
+    await program.methods.refundEscrow(unrouted).accounts(settleAccounts).rpc();
+    assert.isNull(await provider.connection.getAccountInfo(escrowPda));
+  });
+
+  it("Charges subscriptions within their own share of the delegate approval", async () => {
+    const { mint, ata } = await createFundedMint(1000000);
+    const [recipientAta] = await routeToNewRecipients(alias, mint, [10000]);
+    const otherAlias = `${alias}_s`;
+    await program.methods.registerAlias(otherAlias, metadataUri).accounts({ user: provider.wallet.publicKey }).rpc();
+
+    const subscriptionPda = (subscribedAlias: string) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("subscription"), Buffer.from(subscribedAlias), provider.wallet.publicKey.toBuffer(), mint.toBuffer()],
+        program.programId
+      )[0];
+    const cancel = (subscribedAlias: string) =>
+      program.methods
+        .cancelSubscription(subscribedAlias)
+        .accounts({
+          subscription: subscriptionPda(subscribedAlias),
+          payer: provider.wallet.publicKey,
+          payerTokenAccount: ata,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          authority: provider.wallet.publicKey,
+        })
+        .rpc();
+
+    // Two subscriptions on the same token account add up on the single SPL delegate
+    for (const [subscribedAlias, allowance] of [[alias, 3000], [otherAlias, 2000]] as const) {
+      await program.methods
+        .createSubscription(subscribedAlias, new anchor.BN(1000), new anchor.BN(86400), new anchor.BN(allowance))
+        .accounts({ user: provider.wallet.publicKey, mint, userTokenAccount: ata })
+        .rpc();
+    }
+    assert.equal(Number((await getAccount(provider.connection, ata)).delegatedAmount), 5000);
+
+    await program.methods
+      .collectSubscription(alias)
+      .accounts({ subscription: subscriptionPda(alias), payerTokenAccount: ata })
+      .remainingAccounts([{ pubkey: recipientAta, isWritable: true, isSigner: false }])
+      .rpc();
+    assert.equal(await tokenBalance(recipientAta), 1000);
+    assert.equal((await program.account.subscription.fetch(subscriptionPda(alias))).allowance.toNumber(), 2000);
+
+    // The next period is not due yet
+    await expectError(
+      () =>
+        program.methods
+          .collectSubscription(alias)
+          .accounts({ subscription: subscriptionPda(alias), payerTokenAccount: ata })
+          .remainingAccounts([{ pubkey: recipientAta, isWritable: true, isSigner: false }])
+          .rpc(),
+      "SubscriptionNotDue"
+    );
+
+    // Cancelling one subscription only takes back its own unused allowance; the last one revokes
+    await cancel(alias);
+    assert.equal(Number((await getAccount(provider.connection, ata)).delegatedAmount), 2000);
+    await cancel(otherAlias);
+    const account = await getAccount(provider.connection, ata);
+    assert.isNull(account.delegate);
+    assert.equal(Number(account.delegatedAmount), 0);
+  });
+
+  it("Streams tokens and splits them exactly between route and sender on cancel", async () => {
+    const total = 3600000;
+    const { mint, ata } = await createFundedMint(total);
+    const [recipientAta] = await routeToNewRecipients(alias, mint, [10000]);
+    const streamId = new anchor.BN(1);
+    const stream = streamPda(alias, streamId);
+    const start = (await clusterTime()) - 10;
+    const end = start + 3600;
+    // Linear vesting as computed on chain, at the timestamp the program recorded
+    const vestedAt = (timestamp: anchor.BN) => Math.floor((total * (timestamp.toNumber() - start)) / (end - start));
+
+    await program.methods
+      .createStream(alias, streamId, new anchor.BN(total), new anchor.BN(start), new anchor.BN(end))
+      .accounts({ user: provider.wallet.publicKey, mint, userTokenAccount: ata })
+      .rpc();
+    assert.equal(await tokenBalance(ata), 0);
+
+    const withdrawn = await eventOf(
+      await program.methods
+        .withdrawStream(alias)
+        .accounts({ stream })
+        .remainingAccounts([{ pubkey: recipientAta, isWritable: true, isSigner: false }])
+        .rpc({ commitment: "confirmed" }),
+      "streamWithdrawnEvent"
+    );
+    assert.equal(withdrawn.withdrawn.toNumber(), vestedAt(withdrawn.timestamp));
+    assert.equal(await tokenBalance(recipientAta), withdrawn.amount.toNumber());
+
+    const cancelled = await eventOf(
+      await program.methods
+        .cancelStream(alias)
+        .accounts({ stream, sender: provider.wallet.publicKey, senderTokenAccount: ata, authority: provider.wallet.publicKey })
+        .remainingAccounts([{ pubkey: recipientAta, isWritable: true, isSigner: false }])
+        .rpc({ commitment: "confirmed" }),
+      "streamCancelledEvent"
+    );
+    const vested = vestedAt(cancelled.timestamp);
+    assert.equal(cancelled.released.toNumber(), vested - withdrawn.withdrawn.toNumber());
+    assert.equal(cancelled.returned.toNumber(), total - vested);
+    assert.equal(await tokenBalance(recipientAta), vested);
+    assert.equal(await tokenBalance(ata), total - vested);
+    assert.isNull(await provider.connection.getAccountInfo(stream));
+  });
+
+  it("Returns a whole stream to the sender once the route is deleted", async () => {
+    const streamAlias = `${alias}_t`;
+    const { mint, ata } = await createFundedMint(1000000);
+    await program.methods.registerAlias(streamAlias, metadataUri).accounts({ user: provider.wallet.publicKey }).rpc();
+    await program.methods.initRouteConfig(streamAlias).accounts({ user: provider.wallet.publicKey }).rpc();
+
+    const streamId = new anchor.BN(1);
+    const stream = streamPda(streamAlias, streamId);
+    const start = (await clusterTime()) - 100;
+    await program.methods
+      .createStream(streamAlias, streamId, new anchor.BN(1000000), new anchor.BN(start), new anchor.BN(start + 50))
+      .accounts({ user: provider.wallet.publicKey, mint, userTokenAccount: ata })
+      .rpc();
+
+    // Fully vested, but an empty route can't take it
+    await expectError(() => program.methods.withdrawStream(streamAlias).accounts({ stream }).rpc(), "RouteNotConfigured");
+
+    await program.methods.deleteRouteConfig(streamAlias).accounts({ user: provider.wallet.publicKey }).rpc();
+    await program.methods
+      .cancelStream(streamAlias)
+      .accounts({ stream, sender: provider.wallet.publicKey, senderTokenAccount: ata, authority: provider.wallet.publicKey })
+      .rpc();
+    assert.equal(await tokenBalance(ata), 1000000);
+  });
+
+  it("Pays several aliases in one batch with a referral and a pinned route", async () => {
+    const batchAlias = `${alias}_b`;
+    const { mint, ata } = await createFundedMint(1000000);
+    await program.methods.registerAlias(batchAlias, metadataUri).accounts({ user: provider.wallet.publicKey }).rpc();
+    await program.methods.initRouteConfig(batchAlias).accounts({ user: provider.wallet.publicKey }).rpc();
+    const [first] = await routeToNewRecipients(alias, mint, [10000]);
+    const [second, third] = await routeToNewRecipients(batchAlias, mint, [5000, 5000]);
+    const referrer = anchor.web3.Keypair.generate().publicKey;
+    const referrerAta = await createAssociatedTokenAccount(provider.connection, payer, mint, referrer);
+    await program.methods.setReferralPolicy(alias, 1000, true, []).accounts({ user: provider.wallet.publicKey }).rpc();
+
+    const quote = await program.methods
+      .quotePayment(batchAlias, new anchor.BN(20000), mint, 0, provider.wallet.publicKey)
+      .accounts({ tokenMint: mint, referralPolicy: null, referrer: null })
+      .view();
+    const batch = (secondAtas: anchor.web3.PublicKey[], secondRouteHash: number[]) =>
+      program.methods
+        .batchExecuteTokenTransfer([
+          { alias, amount: new anchor.BN(10000), referralBps: 1000, expectedRouteHash: null },
+          { alias: batchAlias, amount: new anchor.BN(20000), referralBps: 0, expectedRouteHash: secondRouteHash },
+        ])
+        .accounts({ user: provider.wallet.publicKey, mint, userTokenAccount: ata, reference: null })
+        .remainingAccounts([
+          ...batchEntryAccounts(alias, mint, [first], referrer),
+          ...batchEntryAccounts(batchAlias, mint, secondAtas),
+        ]);
+
+    await batch([second, third], quote.routeHash).rpc();
+    assert.equal(await tokenBalance(referrerAta), 1000);
+    assert.equal(await tokenBalance(first), 9000);
+    assert.equal(await tokenBalance(second), 10000);
+    assert.equal(await tokenBalance(third), 10000);
+
+    // An entry short of recipient ATAs fails the whole batch
+    await expectError(() => batch([second], quote.routeHash).rpc(), "MissingRecipient");
+    assert.equal(await tokenBalance(first), 9000);
+
+    // A route edited since the quote fails the whole batch
+    await program.methods
+      .setRouteConfig(batchAlias, [
+        { recipient: (await getAccount(provider.connection, third)).owner, percentage: 5000 },
+        { recipient: (await getAccount(provider.connection, second)).owner, percentage: 5000 },
+      ])
+      .accounts({ user: provider.wallet.publicKey })
+      .rpc();
+    await expectError(() => batch([third, second], quote.routeHash).rpc(), "RouteChanged");
+    assert.equal(await tokenBalance(first), 9000);
+
+    await program.methods.deleteReferralPolicy(alias).accounts({ user: provider.wallet.publicKey }).rpc();
+  });
+
+  it("Delivers SOL to each split in its preferred form", async () => {
+    const [native, wrapped] = [anchor.web3.Keypair.generate().publicKey, anchor.web3.Keypair.generate().publicKey];
+    await program.methods
+      .setRouteConfig(alias, [
+        { recipient: native, percentage: 6000 },
+        { recipient: wrapped, percentage: 4000 },
+      ])
+      .accounts({ user: provider.wallet.publicKey })
+      .rpc();
+    const wrappedAta = await createAssociatedTokenAccount(provider.connection, payer, NATIVE_MINT, wrapped);
+    await program.methods
+      .setSplitDelivery(alias, [{ native: {} }, { wrapped: {} }])
+      .accounts({ user: provider.wallet.publicKey })
+      .rpc();
+
+    // Native SOL payment: the wrapped split is paid into its wSOL ATA
+    const solQuote = await program.methods
+      .quotePayment(alias, new anchor.BN(10000000), anchor.web3.PublicKey.default, 0, provider.wallet.publicKey)
+      .accounts({ tokenMint: null, referralPolicy: null, referrer: null })
+      .view();
+    assert.ok(solQuote.legs[0].account.equals(native));
+    assert.ok(solQuote.legs[1].account.equals(wrappedAta));
+    await program.methods
+      .executeTransfer(alias, new anchor.BN(10000000), null, 0, null)
+      .accounts({
+        user: provider.wallet.publicKey,
+        reference: null,
+        receipt: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        referralPolicy: null,
+        referrer: null,
+      })
+      .remainingAccounts(solQuote.legs.map((leg: any) => meta(leg.account, true)))
+      .rpc();
+    assert.equal(await provider.connection.getBalance(native), 6000000);
+    assert.equal(await tokenBalance(wrappedAta), 4000000);
+
+    // wSOL payment: the native split is unwrapped, even if someone pre-funded the unwrap PDA
+    const userWsol = await createWrappedNativeAccount(provider.connection, payer, payer.publicKey, 20000000);
+    const unwrapAccount = pda("unwrap", provider.wallet.publicKey);
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({ fromPubkey: payer.publicKey, toPubkey: unwrapAccount, lamports: 1000000 })
+      )
+    );
+    const wsolQuote = await program.methods
+      .quotePayment(alias, new anchor.BN(10000000), NATIVE_MINT, 0, provider.wallet.publicKey)
+      .accounts({ tokenMint: NATIVE_MINT, referralPolicy: null, referrer: null })
+      .view();
+    assert.ok(wsolQuote.legs[0].account.equals(native));
+    assert.ok(wsolQuote.legs[1].account.equals(wrappedAta));
+    await program.methods
+      .executeTokenTransfer(alias, new anchor.BN(10000000), null, 0, null)
+      .accounts({
+        user: provider.wallet.publicKey,
+        mint: NATIVE_MINT,
+        userTokenAccount: userWsol,
+        reference: null,
+        receipt: null,
+        unwrapAccount,
+        referralPolicy: null,
+        referrer: null,
+        referrerTokenAccount: null,
+      })
+      .remainingAccounts(wsolQuote.legs.map((leg: any) => meta(leg.account, true)))
+      .rpc();
+    assert.equal(await provider.connection.getBalance(native), 12000000);
+    assert.equal(await tokenBalance(wrappedAta), 8000000);
+    assert.isNull(await provider.connection.getAccountInfo(unwrapAccount));
+
+    // A batch can't unwrap, so it refuses rather than paying the native split in wSOL
+    await expectError(
+      () =>
+        program.methods
+          .batchExecuteTokenTransfer([{ alias, amount: new anchor.BN(10000000), referralBps: 0, expectedRouteHash: null }])
+          .accounts({ user: provider.wallet.publicKey, mint: NATIVE_MINT, userTokenAccount: userWsol, reference: null })
+          .remainingAccounts(batchEntryAccounts(alias, NATIVE_MINT, wsolQuote.legs.map((leg: any) => leg.account)))
+          .rpc(),
+      "SolDeliveryUnsupported"
+    );
+  });
+
+  it("Recipient accepts split", async () => {
+    const recipient1 = anchor.web3.Keypair.generate();
+    const recipient2 = anchor.web3.Keypair.generate();
+
+    const splits = [
+      { recipient: recipient1.publicKey, percentage: 7000 },
+      { recipient: recipient2.publicKey, percentage: 3000 },
+    ];
+
+    await program.methods
+      .setRouteConfig(alias, splits)
+      .accounts({
+        user: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    await program.methods
+      .acceptSplit(alias)
+      .accounts({
+        recipient: recipient2.publicKey,
+      })
+      .signers([recipient2])
+      .rpc();
+
+    const account = await program.account.routeAccount.fetch(routePda);
+    assert.equal(account.consentedMask, 0b10);
+  });
+
+  it("Quotes a payment", async () => {
+    const quote = await program.methods
+      .quotePayment(alias, new anchor.BN(10001), anchor.web3.PublicKey.default, 0, provider.wallet.publicKey)
+      .accounts({ tokenMint: null, referralPolicy: null, referrer: null })
+      .view();
+
+    const route = await program.account.routeAccount.fetch(routePda);
+    assert.equal(quote.legs.length, 2);
+    assert.ok(quote.legs[0].account.equals(route.splits[0].recipient));
+    assert.equal(quote.legs[0].amount.toNumber(), 7000);
+    // Rounding dust goes to the last recipient
+    assert.equal(quote.legs[1].amount.toNumber(), 3001);
+    assert.equal(quote.fee.toNumber(), 0);
+    assert.isNull(quote.referral);
+  });
+
+  it("Fail: Route changed since quote", async () => {
+    const quote = await program.methods
+      .quotePayment(alias, new anchor.BN(10000000), anchor.web3.PublicKey.default, 0, provider.wallet.publicKey)
+      .accounts({ tokenMint: null, referralPolicy: null, referrer: null })
+      .view();
+
+    const route = await program.account.routeAccount.fetch(routePda);
+    const changed = route.splits.map((s: any, i: number) => ({
+      recipient: s.recipient,
+      percentage: route.splits[route.splits.length - 1 - i].percentage,
+    }));
+    await program.methods
+      .setRouteConfig(alias, changed)
+      .accounts({
+        user: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    await expectError(
+      () =>
+        program.methods
+          .executeTransfer(alias, new anchor.BN(10000000), null, 0, quote.routeHash)
+          .accounts({
+            user: provider.wallet.publicKey,
+            reference: null,
+            receipt: null,
+            referralPolicy: null,
+            referrer: null,
+          })
+          .remainingAccounts(route.splits.map((s: any) => ({ pubkey: s.recipient, isWritable: true, isSigner: false })))
+          .rpc(),
+      "RouteChanged"
+    );
+
+    await program.methods
+      .setRouteConfig(alias, route.splits)
+      .accounts({
+        user: provider.wallet.publicKey,
+      })
+      .rpc();
+  });
+
+  it("Fail: Recipients out of split order", async () => {
+    const route = await program.account.routeAccount.fetch(routePda);
+    const error = await expectError(
+      () =>
+        program.methods
+          .executeTransfer(alias, new anchor.BN(10000000), null, 0, null)
+          .accounts({
+            user: provider.wallet.publicKey,
+            reference: null,
+            receipt: null,
+            referralPolicy: null,
+            referrer: null,
+          })
+          .remainingAccounts(
+            [...route.splits].reverse().map((s: any) => ({ pubkey: s.recipient, isWritable: true, isSigner: false }))
+          )
+          .rpc(),
+      "InvalidRecipientAccount"
+    );
+    assert.ok(error.logs.some((log) => log.includes("Split 0")), "Expected the mismatch to name split 0");
+  });
+
+  it("Fail: SOL transfers paused", async () => {
+    const [configPda] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("config")], program.programId);
+    if (!(await program.account.config.fetchNullable(configPda))) {
+      const [programData] = anchor.web3.PublicKey.findProgramAddressSync(
+        [program.programId.toBuffer()],
+        new anchor.web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+      );
+      await program.methods
+        .initConfig(provider.wallet.publicKey)
+        .accounts({
+          authority: provider.wallet.publicKey,
+          programData,
+        })
+        .rpc();
+    }
+
+    const PAUSE_SOL_TRANSFERS = 1 << 2;
+    await program.methods.setPaused(PAUSE_SOL_TRANSFERS).accounts({ signer: provider.wallet.publicKey }).rpc();
+
+    const route = await program.account.routeAccount.fetch(routePda);
+    try {
+      await expectError(
+        () =>
+          program.methods
+            .executeTransfer(alias, new anchor.BN(10000000), null, 0, null)
+            .accounts({
+              user: provider.wallet.publicKey,
+              reference: null,
+              receipt: null,
+              referralPolicy: null,
+              referrer: null,
+            })
+            .remainingAccounts(route.splits.map((s: any) => ({ pubkey: s.recipient, isWritable: true, isSigner: false })))
+            .rpc(),
+        "ProtocolPaused"
+      );
+    } finally {
+      await program.methods.setPaused(0).accounts({ signer: provider.wallet.publicKey }).rpc();
+    }
+  });
+
+  it("Fail: Payment to a frozen alias", async () => {
+    const wallet = provider.wallet.publicKey;
+    // A stream already partly vested when the freeze lands
+    const { mint, ata } = await createFundedMint(1000000);
+    const streamId = new anchor.BN(2);
+    const stream = streamPda(alias, streamId);
+    const start = (await clusterTime()) - 100;
+    await program.methods
+      .createStream(alias, streamId, new anchor.BN(1000000), new anchor.BN(start), new anchor.BN(start + 3600))
+      .accounts({ user: wallet, mint, userTokenAccount: ata })
+      .rpc();
+    const route = await program.account.routeAccount.fetch(routePda);
+    const recipientAtas = route.splits.map((s: any) => getAssociatedTokenAddressSync(mint, s.recipient));
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        ...route.splits.map((s: any, i: number) =>
+          createAssociatedTokenAccountIdempotentInstruction(wallet, recipientAtas[i], s.recipient, mint)
+        )
+      )
+    );
+    const cancelStream = () =>
+      program.methods
+        .cancelStream(alias)
+        .accounts({ stream, sender: wallet, senderTokenAccount: ata, authority: wallet })
+        .remainingAccounts(recipientAtas.map((account: anchor.web3.PublicKey) => meta(account, true)))
+        .rpc();
+
+    await program.methods.setConfigAuthorities(wallet, wallet, wallet).accounts({ signer: wallet }).rpc();
+    await program.methods.freezeAlias(alias, 7).accounts({ authority: wallet }).rpc();
+
+    const frozen = await program.account.aliasAccount.fetch(aliasPda);
+    assert.equal(frozen.freezeReason, 7);
+    assert.ok(frozen.frozenBy.equals(wallet));
+
+    try {
+      await expectError(
+        () =>
+          program.methods
+            .quotePayment(alias, new anchor.BN(10000000), anchor.web3.PublicKey.default, 0, provider.wallet.publicKey)
+            .accounts({ tokenMint: null, referralPolicy: null, referrer: null })
+            .view(),
+        "AliasFrozen"
+      );
+      // Cancelling would pay the vested part into the frozen route
+      await expectError(cancelStream, "AliasFrozen");
+    } finally {
+      await program.methods.unfreezeAlias(alias).accounts({ authority: wallet }).rpc();
+    }
+
+    assert.equal((await program.account.aliasAccount.fetch(aliasPda)).freezeReason, 0);
+    await cancelStream();
+  });
+
+  it("Fail: Payment below alias minimum", async () => {
+    // Native SOL limits are keyed by the all-zero mint
+    await program.methods
+      .setPaymentLimits(alias, anchor.web3.PublicKey.default, new anchor.BN(1000000), null)
+      .accounts({
+        user: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    await expectError(
+      () =>
+        program.methods
+          .quotePayment(alias, new anchor.BN(999999), anchor.web3.PublicKey.default, 0, provider.wallet.publicKey)
+          .accounts({ tokenMint: null, referralPolicy: null, referrer: null })
+          .view(),
+      "AmountTooSmall"
+    );
+
+    await program.methods
+      .deletePaymentLimits(alias, anchor.web3.PublicKey.default)
+      .accounts({
+        user: provider.wallet.publicKey,
+      })
+      .rpc();
+  });
+
   // --- Negative Tests (V1 Hardening) ---
 
   it("Fail: Duplicate alias registration", async () => {
@@ -247,4 +1057,508 @@ describe("unik_anchor", () => {
       );
     }
   });
+
+  it("Publishes the accepted mints for an alias", async () => {
+    const usdc = anchor.web3.Keypair.generate().publicKey;
+    const [mintFilterPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("mints"), Buffer.from(alias)],
+      program.programId
+    );
+
+    await program.methods
+      .setMintFilter(alias, { allow: {} }, [usdc])
+      .accounts({ user: provider.wallet.publicKey })
+      .rpc();
+
+    const filter = await program.account.mintFilter.fetch(mintFilterPda);
+    assert.ok(filter.mode.allow);
+    assert.equal(filter.mints.length, 1);
+    assert.ok(filter.mints[0].equals(usdc));
+
+    // Escrows, streams and subscriptions in any other mint are refused up front
+    const { mint, ata } = await createFundedMint(1000000);
+    const user = provider.wallet.publicKey;
+    const start = await clusterTime();
+    await expectError(
+      () =>
+        program.methods
+          .createTokenEscrow(alias, new anchor.BN(7), new anchor.BN(1000), new anchor.BN(3600), null)
+          .accounts({ user, mint, userTokenAccount: ata })
+          .rpc(),
+      "MintNotAccepted"
+    );
+    await expectError(
+      () =>
+        program.methods
+          .createStream(alias, new anchor.BN(7), new anchor.BN(1000), new anchor.BN(start), new anchor.BN(start + 3600))
+          .accounts({ user, mint, userTokenAccount: ata })
+          .rpc(),
+      "MintNotAccepted"
+    );
+    await expectError(
+      () =>
+        program.methods
+          .createSubscription(alias, new anchor.BN(1000), new anchor.BN(86400), new anchor.BN(1000))
+          .accounts({ user, mint, userTokenAccount: ata })
+          .rpc(),
+      "MintNotAccepted"
+    );
+
+    await program.methods.deleteMintFilter(alias).accounts({ user: provider.wallet.publicKey }).rpc();
+    assert.isNull(await provider.connection.getAccountInfo(mintFilterPda));
+  });
+
+  it("Hands an alias to whoever receives its NFT", async () => {
+    const tokenized = `nft_${Date.now()}`;
+    const buyer = anchor.web3.Keypair.generate();
+    const [tokenizedPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("alias"), Buffer.from(tokenized)],
+      program.programId
+    );
+    const [nftMint] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("alias_nft"), Buffer.from(tokenized)],
+      program.programId
+    );
+
+    await program.methods.registerAlias(tokenized, metadataUri).accounts({ user: provider.wallet.publicKey }).rpc();
+    await program.methods.initRouteConfig(tokenized).accounts({ user: provider.wallet.publicKey }).rpc();
+    await routeToNewRecipients(tokenized, NATIVE_MINT, [4000, 6000]);
+    await program.methods
+      .listAlias(tokenized, new anchor.BN(100000000), anchor.web3.PublicKey.default)
+      .accounts({ user: provider.wallet.publicKey, listingSeller: null })
+      .rpc();
+    await program.methods.tokenizeAlias(tokenized).accounts({ user: provider.wallet.publicKey }).rpc();
+
+    let account = await program.account.aliasAccount.fetch(tokenizedPda);
+    assert.ok(account.nftMint.equals(nftMint));
+
+    const from = getAssociatedTokenAddressSync(nftMint, provider.wallet.publicKey, false, TOKEN_2022_PROGRAM_ID);
+    const to = getAssociatedTokenAddressSync(nftMint, buyer.publicKey, false, TOKEN_2022_PROGRAM_ID);
+    const transfer = await createTransferCheckedWithTransferHookInstruction(
+      provider.connection,
+      from,
+      nftMint,
+      to,
+      provider.wallet.publicKey,
+      BigInt(1),
+      0,
+      [],
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        createAssociatedTokenAccountIdempotentInstruction(
+          provider.wallet.publicKey,
+          to,
+          buyer.publicKey,
+          nftMint,
+          TOKEN_2022_PROGRAM_ID
+        ),
+        transfer
+      )
+    );
+
+    account = await program.account.aliasAccount.fetch(tokenizedPda);
+    assert.ok(account.owner.equals(buyer.publicKey));
+    assert.equal(account.version.toNumber(), 2);
+    // The previous holder's splits don't travel with the NFT
+    const route = await program.account.routeAccount.fetch(pda("route", tokenized));
+    assert.equal(route.splits.length, 1);
+    assert.ok(route.splits[0].recipient.equals(buyer.publicKey));
+    assert.equal(route.splits[0].percentage, 10000);
+    assert.equal(route.consentedMask, 0);
+
+    // Once managed directly again, the new owner's listing replaces the one the previous holder left
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: provider.wallet.publicKey,
+          toPubkey: buyer.publicKey,
+          lamports: 100000000,
+        })
+      )
+    );
+    await program.methods
+      .detokenizeAlias(tokenized)
+      .accounts({ user: buyer.publicKey, holderTokenAccount: to })
+      .signers([buyer])
+      .rpc();
+    const listing = pda("listing", tokenized);
+    const relist = (listingSeller: anchor.web3.PublicKey | null) =>
+      program.methods
+        .listAlias(tokenized, new anchor.BN(50000000), anchor.web3.PublicKey.default)
+        .accounts({ user: buyer.publicKey, listingSeller })
+        .signers([buyer])
+        .rpc({ commitment: "confirmed" });
+    await expectError(() => relist(null), "InvalidRecipientAccount");
+
+    const rent = await provider.connection.getBalance(listing);
+    const balanceBefore = await provider.connection.getBalance(provider.wallet.publicKey);
+    const signature = await relist(provider.wallet.publicKey);
+    const { fee } = (
+      await provider.connection.getTransaction(signature, { commitment: "confirmed", maxSupportedTransactionVersion: 0 })
+    ).meta;
+    assert.equal(await provider.connection.getBalance(provider.wallet.publicKey), balanceBefore + rent - fee);
+    const relisted = await program.account.listing.fetch(listing);
+    assert.ok(relisted.seller.equals(buyer.publicKey));
+    assert.equal(relisted.price.toNumber(), 50000000);
+  });
+
+  it("Sells a listed alias and resets its route to the buyer", async () => {
+    const forSale = `sale_${Date.now()}`;
+    const buyer = anchor.web3.Keypair.generate();
+    const price = new anchor.BN(100000000);
+    const [forSalePda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("alias"), Buffer.from(forSale)],
+      program.programId
+    );
+    const [forSaleRoute] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("route"), Buffer.from(forSale)],
+      program.programId
+    );
+
+    await program.methods.registerAlias(forSale, metadataUri).accounts({ user: provider.wallet.publicKey }).rpc();
+    await program.methods.initRouteConfig(forSale).accounts({ user: provider.wallet.publicKey }).rpc();
+    await program.methods
+      .listAlias(forSale, price, anchor.web3.PublicKey.default)
+      .accounts({ user: provider.wallet.publicKey, listingSeller: null })
+      .rpc();
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: provider.wallet.publicKey,
+          toPubkey: buyer.publicKey,
+          lamports: 200000000,
+        })
+      )
+    );
+
+    await program.methods
+      .buyAlias(forSale, price)
+      .accounts({
+        seller: provider.wallet.publicKey,
+        buyer: buyer.publicKey,
+        treasury: null,
+        buyerTokenAccount: null,
+        sellerTokenAccount: null,
+        treasuryTokenAccount: null,
+        tokenProgram: null,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const account = await program.account.aliasAccount.fetch(forSalePda);
+    assert.ok(account.owner.equals(buyer.publicKey));
+    assert.equal(account.version.toNumber(), 2);
+    const route = await program.account.routeAccount.fetch(forSaleRoute);
+    assert.equal(route.splits.length, 1);
+    assert.ok(route.splits[0].recipient.equals(buyer.publicKey));
+  });
+
+  it("Lets the alias owner decline an offer", async () => {
+    const offered = `${alias}_o`;
+    const buyer = anchor.web3.Keypair.generate();
+    const offer = pda("offer", offered, buyer.publicKey);
+    await program.methods.registerAlias(offered, metadataUri).accounts({ user: provider.wallet.publicKey }).rpc();
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: provider.wallet.publicKey,
+          toPubkey: buyer.publicKey,
+          lamports: 100000000,
+        })
+      )
+    );
+    await program.methods
+      .makeOffer(offered, new anchor.BN(50000000))
+      .accounts({ buyer: buyer.publicKey })
+      .signers([buyer])
+      .rpc();
+    assert.equal((await program.account.aliasAccount.fetch(pda("alias", offered))).openObligations, 1);
+
+    const buyerBalance = await provider.connection.getBalance(buyer.publicKey);
+    const offerBalance = await provider.connection.getBalance(offer);
+    await program.methods
+      .cancelOffer(offered)
+      .accounts({
+        offer,
+        buyer: buyer.publicKey,
+        vault: null,
+        buyerTokenAccount: null,
+        tokenProgram: null,
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    assert.isNull(await provider.connection.getAccountInfo(offer));
+    assert.equal(await provider.connection.getBalance(buyer.publicKey), buyerBalance + offerBalance);
+    assert.equal((await program.account.aliasAccount.fetch(pda("alias", offered))).openObligations, 0);
+  });
+
+  it("Auctions an alias to the highest bidder", async () => {
+    const premium = `auction_${Date.now()}`;
+    const treasury = anchor.web3.Keypair.generate().publicKey;
+    const [premiumPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("alias"), Buffer.from(premium)],
+      program.programId
+    );
+
+    // The config exists since the pause test, with the wallet as authority
+    await program.methods.setMarketplaceFee(treasury, 0).accounts({ signer: provider.wallet.publicKey }).rpc();
+    const now = await clusterTime();
+    await program.methods
+      .createAuction(premium, new anchor.BN(1000000), new anchor.BN(now - 10), new anchor.BN(15), new anchor.BN(0))
+      .accounts({ authority: provider.wallet.publicKey })
+      .rpc();
+
+    await expectError(
+      () => program.methods.registerAlias(premium, metadataUri).accounts({ user: provider.wallet.publicKey }).rpc(),
+      "AliasInAuction"
+    );
+
+    await program.methods
+      .placeBid(premium, new anchor.BN(2000000))
+      .accounts({ bidder: provider.wallet.publicKey, previousBidder: null })
+      .rpc();
+
+    // Settlement is checked against the validator clock, so wait on that rather than wall time
+    const { endTs } = await program.account.auction.fetch(pda("auction", premium));
+    while ((await clusterTime()) < endTs.toNumber()) {
+      await new Promise((resolve) => setTimeout(resolve, 400));
+    }
+    await program.methods
+      .settleAuction(premium)
+      .accounts({ aliasAccount: premiumPda, treasury, creator: provider.wallet.publicKey, payer: provider.wallet.publicKey })
+      .rpc();
+
+    const account = await program.account.aliasAccount.fetch(premiumPda);
+    assert.ok(account.owner.equals(provider.wallet.publicKey));
+    assert.equal(await provider.connection.getBalance(treasury), 2000000);
+  });
+
+  it("Deletes an alias together with its route and stats", async () => {
+    const doomed = `doomed_${Date.now()}`;
+    const sol = anchor.web3.PublicKey.default;
+    const [doomedRoute] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("route"), Buffer.from(doomed)],
+      program.programId
+    );
+    const [doomedStats] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("stats"), Buffer.from(doomed), sol.toBuffer()],
+      program.programId
+    );
+
+    await program.methods.registerAlias(doomed, metadataUri).accounts({ user: provider.wallet.publicKey }).rpc();
+    await program.methods.initRouteConfig(doomed).accounts({ user: provider.wallet.publicKey }).rpc();
+    await program.methods.initPaymentStats(doomed, sol).accounts({ user: provider.wallet.publicKey }).rpc();
+
+    // An open escrow would otherwise pass to whoever registers the name next
+    const escrowId = new anchor.BN(1);
+    const [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), Buffer.from(doomed), provider.wallet.publicKey.toBuffer(), escrowId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods
+      .createEscrow(doomed, escrowId, new anchor.BN(5000000), new anchor.BN(3600), null)
+      .accounts({ user: provider.wallet.publicKey })
+      .rpc();
+    await expectError(
+      () =>
+        program.methods
+          .deleteAlias(doomed)
+          .accounts({ user: provider.wallet.publicKey, listingSeller: null })
+          .remainingAccounts([{ pubkey: doomedStats, isWritable: true, isSigner: false }])
+          .rpc(),
+      "AliasHasOpenObligations"
+    );
+    await program.methods
+      .refundEscrow(doomed)
+      .accounts({
+        escrow: escrowPda,
+        payer: provider.wallet.publicKey,
+        vault: null,
+        payerTokenAccount: null,
+        tokenProgram: null,
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+    assert.equal((await program.account.aliasAccount.fetch(pda("alias", doomed))).openObligations, 0);
+
+    await program.methods
+      .deleteAlias(doomed)
+      .accounts({
+        user: provider.wallet.publicKey,
+        listingSeller: null,
+      })
+      .remainingAccounts([{ pubkey: doomedStats, isWritable: true, isSigner: false }])
+      .rpc();
+
+    assert.isNull(await provider.connection.getAccountInfo(doomedRoute));
+    assert.isNull(await provider.connection.getAccountInfo(doomedStats));
+  });
+
+  it("Lets the owner end a third party's subscription to delete the alias", async () => {
+    const doomed = `doomed_s_${Date.now()}`;
+    const subscriber = anchor.web3.Keypair.generate();
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: provider.wallet.publicKey,
+          toPubkey: subscriber.publicKey,
+          lamports: 100000000,
+        })
+      )
+    );
+    const mint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    const subscriberAta = await createAssociatedTokenAccount(provider.connection, payer, mint, subscriber.publicKey);
+    await mintTo(provider.connection, payer, mint, subscriberAta, payer, 10000);
+
+    await program.methods.registerAlias(doomed, metadataUri).accounts({ user: provider.wallet.publicKey }).rpc();
+    await program.methods
+      .createSubscription(doomed, new anchor.BN(1000), new anchor.BN(86400), new anchor.BN(3000))
+      .accounts({ user: subscriber.publicKey, mint, userTokenAccount: subscriberAta })
+      .signers([subscriber])
+      .rpc();
+    const [subscription] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("subscription"), Buffer.from(doomed), subscriber.publicKey.toBuffer(), mint.toBuffer()],
+      program.programId
+    );
+
+    const deleteAlias = () =>
+      program.methods.deleteAlias(doomed).accounts({ user: provider.wallet.publicKey, listingSeller: null }).rpc();
+    await expectError(deleteAlias, "AliasHasOpenObligations");
+
+    // The owner ends it; the payer gets the rent back and keeps their own delegate approval
+    const before = await provider.connection.getBalance(subscriber.publicKey);
+    await program.methods
+      .cancelSubscription(doomed)
+      .accounts({
+        subscription,
+        payer: subscriber.publicKey,
+        payerTokenAccount: null,
+        tokenProgram: null,
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+    assert.isNull(await provider.connection.getAccountInfo(subscription));
+    assert.isAbove(await provider.connection.getBalance(subscriber.publicKey), before);
+
+    await deleteAlias();
+    assert.isNull(await provider.connection.getAccountInfo(pda("alias", doomed)));
+  });
+
+  it("Records the alias history in typed events", async () => {
+    const historyAlias = `${alias}_h`;
+    const owner = provider.wallet.publicKey;
+    const newMetadataUri = `${metadataUri}/v2`;
+
+    const registered = await eventOf(
+      await program.methods.registerAlias(historyAlias, metadataUri).accounts({ user: owner }).rpc({ commitment: "confirmed" }),
+      "aliasRegisteredEvent"
+    );
+    assert.equal(registered.alias, historyAlias);
+    assert.ok(registered.owner.equals(owner));
+    assert.equal(registered.metadataUri, metadataUri);
+    assert.equal(registered.version.toNumber(), 1);
+
+    const updated = await eventOf(
+      await program.methods
+        .updateAliasMetadata(historyAlias, newMetadataUri)
+        .accounts({ user: owner })
+        .rpc({ commitment: "confirmed" }),
+      "aliasMetadataUpdatedEvent"
+    );
+    assert.equal(updated.alias, historyAlias);
+    assert.equal(updated.oldMetadataUri, metadataUri);
+    assert.equal(updated.newMetadataUri, newMetadataUri);
+    assert.equal(updated.version.toNumber(), 2);
+
+    const deactivated = await eventOf(
+      await program.methods.deactivateAlias(historyAlias).accounts({ user: owner }).rpc({ commitment: "confirmed" }),
+      "aliasDeactivatedEvent"
+    );
+    assert.equal(deactivated.alias, historyAlias);
+    assert.ok(deactivated.owner.equals(owner));
+
+    const reactivated = await eventOf(
+      await program.methods.reactivateAlias(historyAlias).accounts({ user: owner }).rpc({ commitment: "confirmed" }),
+      "aliasReactivatedEvent"
+    );
+    assert.equal(reactivated.alias, historyAlias);
+    assert.ok(reactivated.owner.equals(owner));
+
+    // The last state closes the history
+    const deleted = await eventOf(
+      await program.methods
+        .deleteAlias(historyAlias)
+        .accounts({ user: owner, listingSeller: null })
+        .rpc({ commitment: "confirmed" }),
+      "aliasDeletedEvent"
+    );
+    assert.equal(deleted.alias, historyAlias);
+    assert.ok(deleted.owner.equals(owner));
+    assert.equal(deleted.metadataUri, newMetadataUri);
+    assert.equal(deleted.version.toNumber(), 2);
+  });
+
+  it("Records each route change with its splits and the previous splits hash", async () => {
+    const routeAlias = `${alias}_r`;
+    const user = provider.wallet.publicKey;
+    const routeAccount = pda("route", routeAlias);
+    // RouteAccount::splits_hash: sha256 of the Borsh-encoded Vec<Split>
+    const splitsHash = (splits: { recipient: anchor.web3.PublicKey; percentage: number }[]) => {
+      const data = Buffer.alloc(4 + splits.length * 34);
+      data.writeUInt32LE(splits.length, 0);
+      splits.forEach((split, i) => {
+        split.recipient.toBuffer().copy(data, 4 + i * 34);
+        data.writeUInt16LE(split.percentage, 4 + i * 34 + 32);
+      });
+      return [...createHash("sha256").update(data).digest()];
+    };
+    const newSplits = (percentages: number[]) =>
+      percentages.map((percentage) => ({ recipient: anchor.web3.Keypair.generate().publicKey, percentage }));
+    const setRoute = async (splits: { recipient: anchor.web3.PublicKey; percentage: number }[]) => {
+      const event = await eventOf(
+        await program.methods.setRouteConfig(routeAlias, splits).accounts({ user }).rpc({ commitment: "confirmed" }),
+        "routeSetEvent"
+      );
+      assert.equal(event.alias, routeAlias);
+      assert.deepEqual(
+        event.splits.map((split: any) => [split.recipient.toBase58(), split.percentage]),
+        splits.map((split) => [split.recipient.toBase58(), split.percentage])
+      );
+      return event;
+    };
+
+    await program.methods.registerAlias(routeAlias, metadataUri).accounts({ user }).rpc();
+    await program.methods.initRouteConfig(routeAlias).accounts({ user }).rpc();
+
+    const first = newSplits([6000, 4000]);
+    assert.deepEqual((await setRoute(first)).previousSplitsHash, splitsHash([]));
+    const second = newSplits([10000]);
+    assert.deepEqual((await setRoute(second)).previousSplitsHash, splitsHash(first));
+
+    const deleted = await eventOf(
+      await program.methods.deleteRouteConfig(routeAlias).accounts({ user }).rpc({ commitment: "confirmed" }),
+      "routeDeletedEvent"
+    );
+    assert.equal(deleted.alias, routeAlias);
+    assert.deepEqual(deleted.previousSplitsHash, splitsHash(second));
+
+    await program.methods.initRouteConfig(routeAlias).accounts({ user }).rpc();
+    const third = newSplits([2500, 7500]);
+    assert.deepEqual((await setRoute(third)).previousSplitsHash, splitsHash([]));
+
+    const migrated = await eventOf(
+      await program.methods
+        .migrateRouteAccount(routeAlias)
+        .accounts({ user, routeAccount })
+        .rpc({ commitment: "confirmed" }),
+      "routeMigratedEvent"
+    );
+    assert.equal(migrated.alias, routeAlias);
+    assert.deepEqual(migrated.previousSplitsHash, splitsHash(third));
+    assert.isNull(await provider.connection.getAccountInfo(routeAccount));
+  });
 });